@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::instruction::Instruction;
+
+// Assembler for the mnemonics printed by `Instruction`'s `Display` impl and
+// the disassembler. Besides instructions a source file may contain:
+//
+//   label:              defines a label at the current address
+//   NAME equ 0x10       defines a constant
+//   db 1, 0x2, "##.."   emits bytes, strings of '#'/'.' are sprite rows
+//   dw 0x1234, label    emits big-endian words
+//
+// Operands accept decimal, 0x and 0b literals, labels and constants joined
+// with + and -. Everything after ';' is a comment.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, column: usize, message: String) -> AsmError {
+        AsmError { line, column, message }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// Assembles `source` into a ROM image to be loaded at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::new();
+    for (number, line) in source.lines().enumerate() {
+        assembler.parse_line(number + 1, line)?;
+    }
+    assembler.emit()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(i64),
+    Str(String),
+    Comma,
+    Colon,
+    LBracket,
+    RBracket,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(line_number: usize, line: &str) -> Result<Vec<Token>, AsmError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        let column = pos + 1;
+        if c == ';' {
+            break;
+        }
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let kind = match c {
+            ',' => TokenKind::Comma,
+            ':' => TokenKind::Colon,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '"' => {
+                let end = match chars[pos + 1..].iter().position(|&c| c == '"') {
+                    Some(end) => pos + 1 + end,
+                    None => return Err(AsmError::new(line_number, column, String::from("unterminated string"))),
+                };
+                let text = chars[pos + 1..end].iter().collect();
+                pos = end + 1;
+                tokens.push(Token { kind: TokenKind::Str(text), column });
+                continue;
+            },
+            _ if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let start = pos;
+                while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_' || chars[pos] == '.') {
+                    pos += 1;
+                }
+                let word: String = chars[start..pos].iter().collect();
+                let kind = if c.is_ascii_digit() {
+                    TokenKind::Number(parse_number(&word).ok_or_else(|| {
+                        AsmError::new(line_number, column, format!("invalid number '{}'", word))
+                    })?)
+                } else {
+                    TokenKind::Ident(word)
+                };
+                tokens.push(Token { kind, column });
+                continue;
+            },
+            _ => return Err(AsmError::new(line_number, column, format!("unexpected character '{}'", c))),
+        };
+        tokens.push(Token { kind, column });
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+fn parse_number(word: &str) -> Option<i64> {
+    let lower = word.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+// A sum of numbers and symbols, resolved once all labels are known.
+#[derive(Debug, Clone)]
+struct Expr {
+    terms: Vec<(i64, Term)>,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Number(i64),
+    Symbol(String, usize),
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    V(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long(Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Data {
+    Expr(Expr),
+    Sprite(u8),
+}
+
+#[derive(Debug)]
+enum Item {
+    Instruction { mnemonic: String, operands: Vec<(Operand, usize)>, column: usize },
+    Bytes(Vec<Data>),
+    Words(Vec<Expr>),
+}
+
+struct Assembler {
+    items: Vec<(usize, Item)>,
+    labels: HashMap<String, u32>,
+    constants: HashMap<String, (usize, Expr)>,
+    address: usize,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            items: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            address: Chip8::PROGRAM_START,
+        }
+    }
+
+    fn parse_line(&mut self, line: usize, text: &str) -> Result<(), AsmError> {
+        let tokens = tokenize(line, text)?;
+        let mut rest = &tokens[..];
+
+        while let [Token { kind: TokenKind::Ident(name), column }, Token { kind: TokenKind::Colon, .. }, tail @ ..] = rest {
+            self.define(line, *column, name)?;
+            self.labels.insert(name.clone(), self.address as u32);
+            rest = tail;
+        }
+
+        let (keyword, column, operands) = match rest {
+            [] => return Ok(()),
+            [Token { kind: TokenKind::Ident(name), column }, Token { kind: TokenKind::Ident(equ), .. }, tail @ ..]
+                if equ.eq_ignore_ascii_case("equ") =>
+            {
+                self.define(line, *column, name)?;
+                let expr = parse_expr(line, tail, *column)?;
+                self.constants.insert(name.clone(), (line, expr));
+                return Ok(());
+            },
+            [Token { kind: TokenKind::Ident(keyword), column }, tail @ ..] => (keyword.to_ascii_uppercase(), *column, tail),
+            [token, ..] => return Err(AsmError::new(line, token.column, String::from("expected a mnemonic or directive"))),
+        };
+
+        let item = match &keyword[..] {
+            "DB" => {
+                let mut bytes = Vec::new();
+                for (tokens, column) in split_operands(line, operands, column)? {
+                    match tokens {
+                        [Token { kind: TokenKind::Str(pattern), column }] => bytes.push(Data::Sprite(parse_sprite(line, *column, pattern)?)),
+                        _ => bytes.push(Data::Expr(parse_expr(line, tokens, column)?)),
+                    }
+                }
+                Item::Bytes(bytes)
+            },
+            "DW" => {
+                let mut words = Vec::new();
+                for (tokens, column) in split_operands(line, operands, column)? {
+                    words.push(parse_expr(line, tokens, column)?);
+                }
+                Item::Words(words)
+            },
+            _ => {
+                let mut parsed = Vec::new();
+                for (tokens, column) in split_operands(line, operands, column)? {
+                    parsed.push((parse_operand(line, tokens, column)?, column));
+                }
+                Item::Instruction { mnemonic: keyword, operands: parsed, column }
+            },
+        };
+        let size = match &item {
+            Item::Instruction { operands, .. } if operands.iter().any(|(operand, _)| matches!(operand, Operand::Long(_))) => 4,
+            Item::Instruction { .. } => 2,
+            Item::Bytes(bytes) => bytes.len(),
+            Item::Words(words) => words.len() * 2,
+        };
+        // Programs over 4 KiB get the XO-CHIP address space when loaded.
+        if self.address + size > Chip8::XO_CHIP_MEMORY_SIZE {
+            return Err(AsmError::new(line, column, String::from("program does not fit in memory")));
+        }
+        self.items.push((line, item));
+        self.address += size;
+        Ok(())
+    }
+
+    fn define(&self, line: usize, column: usize, name: &str) -> Result<(), AsmError> {
+        if register_operand(name).is_some() {
+            return Err(AsmError::new(line, column, format!("'{}' is a reserved name", name)));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(AsmError::new(line, column, format!("'{}' is already defined", name)));
+        }
+        Ok(())
+    }
+
+    fn emit(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::new();
+        for (line, item) in &self.items {
+            let line = *line;
+            match item {
+                Item::Instruction { mnemonic, operands, column } => {
+                    rom.extend(self.encode(line, *column, mnemonic, operands)?.to_bytes());
+                },
+                Item::Bytes(bytes) => {
+                    for data in bytes {
+                        match data {
+                            Data::Sprite(byte) => rom.push(*byte),
+                            Data::Expr(expr) => rom.push(self.byte(line, expr)?),
+                        }
+                    }
+                },
+                Item::Words(words) => {
+                    for expr in words {
+                        let word = self.value(line, expr, -0x8000, 0xffff)? as u16;
+                        rom.push((word >> 8) as u8);
+                        rom.push(word as u8);
+                    }
+                },
+            }
+        }
+        Ok(rom)
+    }
+
+    fn encode(&self, line: usize, column: usize, mnemonic: &str, operands: &[(Operand, usize)]) -> Result<Instruction, AsmError> {
+        use self::Instruction::*;
+        use self::Operand::*;
+
+        let ops: Vec<&Operand> = operands.iter().map(|(operand, _)| operand).collect();
+        let instruction = match (mnemonic, &ops[..]) {
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("SCD", [Expr(e)]) => Scd(self.value(line, e, 0, 0xf)? as u8),
+            ("SCU", [Expr(e)]) => Scu(self.value(line, e, 0, 0xf)? as u8),
+            ("SCR", []) => Scr,
+            ("SCL", []) => Scl,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => Low,
+            ("HIGH", []) => High,
+            ("SYS", [Expr(e)]) => Sys(self.address(line, e)?),
+            ("JP", [Expr(e)]) => Jp(self.address(line, e)?),
+            ("JP", [V(0), Expr(e)]) => JpV0(self.address(line, e)?),
+            ("CALL", [Expr(e)]) => Call(self.address(line, e)?),
+            ("SE", [V(x), Expr(e)]) => SeVxByte { x: *x, kk: self.byte(line, e)? },
+            ("SE", [V(x), V(y)]) => SeVxVy { x: *x, y: *y },
+            ("SAVE", [V(x), V(y)]) => Save { x: *x, y: *y },
+            ("LOAD", [V(x), V(y)]) => Load { x: *x, y: *y },
+            ("PLANE", [Expr(e)]) => Plane(self.value(line, e, 0, 3)? as u8),
+            ("AUDIO", []) => Audio,
+            ("PITCH", [V(x)]) => Pitch { x: *x },
+            ("SNE", [V(x), Expr(e)]) => SneVxByte { x: *x, kk: self.byte(line, e)? },
+            ("SNE", [V(x), V(y)]) => SneVxVy { x: *x, y: *y },
+            ("LD", [V(x), Expr(e)]) => LdVxByte { x: *x, kk: self.byte(line, e)? },
+            ("LD", [V(x), V(y)]) => LdVxVy { x: *x, y: *y },
+            ("LD", [I, Expr(e)]) => LdI(self.address(line, e)?),
+            ("LD", [I, Long(e)]) => LdILong(self.value(line, e, 0, 0xffff)? as u16),
+            ("LD", [V(x), Dt]) => LdVxDt { x: *x },
+            ("LD", [V(x), K]) => LdVxK { x: *x },
+            ("LD", [Dt, V(x)]) => LdDtVx { x: *x },
+            ("LD", [St, V(x)]) => LdStVx { x: *x },
+            ("LD", [F, V(x)]) => LdFVx { x: *x },
+            ("LD", [Hf, V(x)]) => LdHfVx { x: *x },
+            ("LD", [R, V(x)]) => LdRVx { x: *x },
+            ("LD", [V(x), R]) => LdVxR { x: *x },
+            ("LD", [B, V(x)]) => LdBVx { x: *x },
+            ("LD", [IndirectI, V(x)]) => LdIVx { x: *x },
+            ("LD", [V(x), IndirectI]) => LdVxI { x: *x },
+            ("ADD", [V(x), Expr(e)]) => AddVxByte { x: *x, kk: self.byte(line, e)? },
+            ("ADD", [V(x), V(y)]) => AddVxVy { x: *x, y: *y },
+            ("ADD", [I, V(x)]) => AddIVx { x: *x },
+            ("OR", [V(x), V(y)]) => Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Sub { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Subn { x: *x, y: *y },
+            ("SHR", [V(x)]) => Shr { x: *x, y: *x },
+            ("SHR", [V(x), V(y)]) => Shr { x: *x, y: *y },
+            ("SHL", [V(x)]) => Shl { x: *x, y: *x },
+            ("SHL", [V(x), V(y)]) => Shl { x: *x, y: *y },
+            ("RND", [V(x), Expr(e)]) => Rnd { x: *x, kk: self.byte(line, e)? },
+            ("DRW", [V(x), V(y), Expr(e)]) => Drw { x: *x, y: *y, n: self.value(line, e, 0, 0xf)? as u8 },
+            ("SKP", [V(x)]) => Skp { x: *x },
+            ("SKNP", [V(x)]) => Sknp { x: *x },
+            (
+                "CLS" | "RET" | "SCD" | "SCU" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "SYS" | "JP" | "CALL" | "SE" | "SNE" | "LD" | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SUBN"
+                | "SHR" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP" | "SAVE" | "LOAD" | "PLANE" | "AUDIO" | "PITCH",
+                _,
+            ) => return Err(AsmError::new(line, column, format!("invalid operands for {}", mnemonic))),
+            _ => return Err(AsmError::new(line, column, format!("unknown mnemonic '{}'", mnemonic))),
+        };
+        Ok(instruction)
+    }
+
+    fn address(&self, line: usize, expr: &Expr) -> Result<u16, AsmError> {
+        Ok(self.value(line, expr, 0, 0xfff)? as u16)
+    }
+
+    // Bytes accept negative values as two's complement, so `ADD V0, -1` works.
+    fn byte(&self, line: usize, expr: &Expr) -> Result<u8, AsmError> {
+        Ok(self.value(line, expr, -0x80, 0xff)? as u8)
+    }
+
+    fn value(&self, line: usize, expr: &Expr, min: i64, max: i64) -> Result<i64, AsmError> {
+        let value = self.evaluate(line, expr, 0)?;
+        if value < min || value > max {
+            return Err(AsmError::new(line, expr.column, format!("value {:#x} out of range", value)));
+        }
+        Ok(value)
+    }
+
+    fn evaluate(&self, line: usize, expr: &Expr, depth: usize) -> Result<i64, AsmError> {
+        let mut total: i64 = 0;
+        for (sign, term) in &expr.terms {
+            let value = match term {
+                Term::Number(value) => *value,
+                Term::Symbol(name, column) => {
+                    if let Some(&address) = self.labels.get(name) {
+                        i64::from(address)
+                    } else if let Some((def_line, def)) = self.constants.get(name) {
+                        if depth > 32 {
+                            return Err(AsmError::new(line, *column, format!("constant '{}' is recursive", name)));
+                        }
+                        self.evaluate(*def_line, def, depth + 1)?
+                    } else {
+                        return Err(AsmError::new(line, *column, format!("undefined symbol '{}'", name)));
+                    }
+                },
+            };
+            total = value.checked_mul(*sign).and_then(|value| total.checked_add(value))
+                .ok_or_else(|| AsmError::new(line, expr.column, String::from("expression overflows")))?;
+        }
+        Ok(total)
+    }
+}
+
+// Splits operand tokens on commas, keeping the column of each operand.
+fn split_operands(line: usize, tokens: &[Token], column: usize) -> Result<Vec<(&[Token], usize)>, AsmError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut operands = Vec::new();
+    for operand in tokens.split(|token| token.kind == TokenKind::Comma) {
+        match operand.first() {
+            Some(first) => operands.push((operand, first.column)),
+            None => return Err(AsmError::new(line, column, String::from("missing operand"))),
+        }
+    }
+    Ok(operands)
+}
+
+fn register_operand(name: &str) -> Option<Operand> {
+    let upper = name.to_ascii_uppercase();
+    let operand = match &upper[..] {
+        "I" => Operand::I,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        _ if upper.len() == 2 && upper.starts_with('V') => Operand::V(u8::from_str_radix(&upper[1..], 16).ok()?),
+        _ => return None,
+    };
+    Some(operand)
+}
+
+fn parse_operand(line: usize, tokens: &[Token], column: usize) -> Result<Operand, AsmError> {
+    match tokens {
+        [Token { kind: TokenKind::Ident(name), .. }] => {
+            if let Some(operand) = register_operand(name) {
+                return Ok(operand);
+            }
+        },
+        [Token { kind: TokenKind::LBracket, .. }, Token { kind: TokenKind::Ident(name), .. }, Token { kind: TokenKind::RBracket, .. }]
+            if name.eq_ignore_ascii_case("I") =>
+        {
+            return Ok(Operand::IndirectI);
+        },
+        [Token { kind: TokenKind::Ident(long), .. }, tail @ ..] if long.eq_ignore_ascii_case("LONG") => {
+            return Ok(Operand::Long(parse_expr(line, tail, column)?));
+        },
+        _ => {},
+    }
+    Ok(Operand::Expr(parse_expr(line, tokens, column)?))
+}
+
+fn parse_expr(line: usize, tokens: &[Token], column: usize) -> Result<Expr, AsmError> {
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut expect_term = true;
+    for token in tokens {
+        match (&token.kind, expect_term) {
+            (TokenKind::Minus, true) => sign = -sign,
+            (TokenKind::Number(value), true) => {
+                terms.push((sign, Term::Number(*value)));
+                expect_term = false;
+            },
+            (TokenKind::Ident(name), true) if register_operand(name).is_none() => {
+                terms.push((sign, Term::Symbol(name.clone(), token.column)));
+                expect_term = false;
+            },
+            (TokenKind::Plus, false) => {
+                sign = 1;
+                expect_term = true;
+            },
+            (TokenKind::Minus, false) => {
+                sign = -1;
+                expect_term = true;
+            },
+            _ => return Err(AsmError::new(line, token.column, String::from("invalid expression"))),
+        }
+    }
+    if expect_term {
+        let column = tokens.last().map_or(column, |token| token.column);
+        return Err(AsmError::new(line, column, String::from("expected a value")));
+    }
+    Ok(Expr { terms, column: tokens.first().map_or(column, |token| token.column) })
+}
+
+// Sprite rows are written left to right, '#' or 'X' for a set pixel and '.'
+// or ' ' for a clear one.
+fn parse_sprite(line: usize, column: usize, pattern: &str) -> Result<u8, AsmError> {
+    if pattern.chars().count() > 8 {
+        return Err(AsmError::new(line, column, String::from("sprite rows are at most 8 pixels wide")));
+    }
+    let mut byte = 0;
+    for (bit, c) in pattern.chars().enumerate() {
+        match c {
+            '#' | 'X' | 'x' => byte |= 0x80 >> bit,
+            '.' | ' ' => {},
+            _ => return Err(AsmError::new(line, column + 1 + bit, format!("invalid sprite pixel '{}'", c))),
+        }
+    }
+    Ok(byte)
+}
@@ -0,0 +1,268 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use crate::chip8::Chip8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+}
+
+impl Waveform {
+    // "square" or "sine".
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            _ => None,
+        }
+    }
+}
+
+// The beep of machines without XO-CHIP audio, which play their own
+// pattern instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    // 0 for silence to 1 for full scale.
+    pub volume: f32,
+}
+
+impl Tone {
+    // The audible range, give or take.
+    pub const MIN_FREQUENCY: f32 = 20.0;
+    pub const MAX_FREQUENCY: f32 = 20000.0;
+}
+
+impl Default for Tone {
+    fn default() -> Tone {
+        Tone { waveform: Waveform::Square, frequency: 440.0, volume: 0.25 }
+    }
+}
+
+// Turns the sound timer into samples. Time is taken from the emulated
+// frames rather than the wall clock, so every frame yields exactly
+// `sample_rate / 60` samples on average and a beep lasts as many samples
+// as the timer says, however the frontend is paced. Each frame is held back
+// until the next one shows whether the beep goes on, so the fades at its
+// edges fit inside it.
+pub struct Beeper {
+    tone: Tone,
+    sample_rate: u32,
+    frame: u64,
+    // The last frame run, not played yet.
+    pending: Option<FrameSound>,
+    // Samples owed to the next frame, in 60ths of a sample.
+    remainder: u32,
+    phase: f32,
+    // Envelope level, ramped over `RAMP` to avoid clicks at the edges.
+    level: f32,
+}
+
+// What a frame sounds like, as of its end.
+#[derive(Debug, Clone, Copy)]
+struct FrameSound {
+    on: bool,
+    // The XO-CHIP pattern and its rate in bits per second.
+    pattern: Option<([u8; 16], f32)>,
+}
+
+impl FrameSound {
+    fn of(chip8: &Chip8) -> FrameSound {
+        let pattern = if chip8.is_xo_chip() {
+            Some((*chip8.audio_pattern(), chip8.audio_rate() as f32))
+        } else {
+            None
+        };
+        FrameSound { on: chip8.sounded(), pattern }
+    }
+}
+
+impl Beeper {
+    // The tone fades in and out over a millisecond.
+    const RAMP: f32 = 0.001;
+
+    pub fn new(tone: Tone, sample_rate: u32) -> Beeper {
+        Beeper { tone, sample_rate, frame: 0, pending: None, remainder: 0, phase: 0.0, level: 0.0 }
+    }
+
+    pub fn tone(&self) -> Tone {
+        self.tone
+    }
+
+    // Appends the samples of the frame before the last one `chip8` ran, so
+    // it has to be called after every frame. Frames skipped by loading a
+    // state aren't played, and rewinding stays silent.
+    pub fn generate(&mut self, chip8: &Chip8, samples: &mut Vec<f32>) {
+        let frames = chip8.frames();
+        let advanced = frames > self.frame;
+        self.frame = frames;
+        if !advanced {
+            return;
+        }
+        let next = FrameSound::of(chip8);
+        if let Some(frame) = self.pending.replace(next) {
+            self.play(frame, next.on, samples);
+        }
+    }
+
+    // Appends the samples of the frame held back by `generate`.
+    pub fn flush(&mut self, samples: &mut Vec<f32>) {
+        if let Some(frame) = self.pending.take() {
+            self.play(frame, false, samples);
+        }
+    }
+
+    // A beep fades in over the start of its first frame and out over the
+    // end of its last, every sample of the frames it lasts is heard.
+    fn play(&mut self, frame: FrameSound, next_on: bool, samples: &mut Vec<f32>) {
+        let total = self.remainder + self.sample_rate;
+        self.remainder = total % 60;
+        let count = total / 60;
+        let ramp = (Beeper::RAMP * self.sample_rate as f32).max(1.0);
+        for n in 0..count {
+            self.level = if !frame.on {
+                0.0
+            } else if next_on {
+                (self.level + 1.0 / ramp).min(1.0)
+            } else {
+                (self.level + 1.0 / ramp).min(1.0).min((count - n) as f32 / ramp)
+            };
+            samples.push(self.sample(frame.pattern) * self.level * self.tone.volume);
+        }
+    }
+
+    fn sample(&mut self, pattern: Option<([u8; 16], f32)>) -> f32 {
+        if let Some((pattern, rate)) = pattern {
+            // The 128-bit pattern looped at the rate set by the pitch register.
+            let bit = self.phase as usize % 128;
+            let on = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            self.phase = (self.phase + rate / self.sample_rate as f32) % 128.0;
+            return if on { 1.0 } else { -1.0 };
+        }
+        let phase = self.phase % 1.0;
+        self.phase = (phase + self.tone.frequency / self.sample_rate as f32) % 1.0;
+        match self.tone.waveform {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+        }
+    }
+}
+
+// Where generated samples go: a sound device, a file or nowhere. Samples
+// are mono, from -1 to 1.
+pub trait AudioBackend {
+    fn sample_rate(&self) -> u32;
+
+    fn play(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    // Flushes whatever is buffered, e.g. to finish a file.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Discards everything, for machines without a sound device.
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn sample_rate(&self) -> u32 {
+        Audio::SAMPLE_RATE
+    }
+
+    fn play(&mut self, _samples: &[f32]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Writes 16-bit mono WAV.
+pub struct WavAudio<W: Write + Seek> {
+    writer: Option<hound::WavWriter<W>>,
+    sample_rate: u32,
+}
+
+impl WavAudio<io::BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<WavAudio<io::BufWriter<File>>> {
+        WavAudio::new(io::BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Seek> WavAudio<W> {
+    pub fn new(output: W) -> io::Result<WavAudio<W>> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: Audio::SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::new(output, spec).map_err(io::Error::other)?;
+        Ok(WavAudio { writer: Some(writer), sample_rate: Audio::SAMPLE_RATE })
+    }
+}
+
+impl<W: Write + Seek> AudioBackend for WavAudio<W> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn play(&mut self, samples: &[f32]) -> io::Result<()> {
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => return Err(io::Error::other("WAV file is already finished")),
+        };
+        for &sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+            writer.write_sample(sample).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(writer) => writer.finalize().map_err(io::Error::other),
+            None => Ok(()),
+        }
+    }
+}
+
+// A beeper feeding a backend, updated after every emulated frame.
+pub struct Audio {
+    beeper: Beeper,
+    backend: Box<dyn AudioBackend>,
+    samples: Vec<f32>,
+}
+
+impl Audio {
+    pub const SAMPLE_RATE: u32 = 44100;
+
+    pub fn new(tone: Tone, backend: Box<dyn AudioBackend>) -> Audio {
+        Audio { beeper: Beeper::new(tone, backend.sample_rate()), backend, samples: Vec::new() }
+    }
+
+    // Plays up to the frame before the last one `chip8` ran.
+    pub fn update(&mut self, chip8: &Chip8) -> io::Result<()> {
+        self.samples.clear();
+        self.beeper.generate(chip8, &mut self.samples);
+        self.play()
+    }
+
+    // Plays the last frame and finishes the backend.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.samples.clear();
+        self.beeper.flush(&mut self.samples);
+        self.play()?;
+        self.backend.finish()
+    }
+
+    fn play(&mut self) -> io::Result<()> {
+        if self.samples.is_empty() {
+            return Ok(());
+        }
+        self.backend.play(&self.samples)
+    }
+}
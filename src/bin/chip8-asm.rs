@@ -0,0 +1,34 @@
+use std::io;
+use std::io::prelude::*;
+use std::fs::File;
+use std::path::Path;
+use std::env;
+
+use chip8_emu::asm::assemble;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("usage: {} <source.asm> [output.ch8]", args[0]);
+        std::process::exit(2);
+    }
+    let input_path = Path::new(&args[1]);
+    let output_path = match args.get(2) {
+        Some(output) => Path::new(output).to_path_buf(),
+        None => input_path.with_extension("ch8"),
+    };
+
+    let mut source = String::new();
+    File::open(input_path)?.read_to_string(&mut source)?;
+
+    let rom = match assemble(&source) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("{}:{}", input_path.display(), err);
+            std::process::exit(1);
+        },
+    };
+    File::create(&output_path)?.write_all(&rom)?;
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use std::io;
+use std::io::prelude::*;
+use std::fs::File;
+use std::env;
+
+use chip8_emu::disasm::disassemble;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: {} <rom.ch8>", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut f = File::open(&args[1])?;
+    let mut rom = Vec::new();
+    f.read_to_end(&mut rom)?;
+
+    println!("; {} ({} bytes)", args[1], rom.len());
+    print!("{}", disassemble(&rom));
+
+    Ok(())
+}
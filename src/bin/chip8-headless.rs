@@ -0,0 +1,256 @@
+use std::io;
+use std::io::prelude::*;
+use std::fs::{self, File};
+use std::path::Path;
+use std::str::FromStr;
+use std::env;
+
+use chip8_emu::movie::KeyEvent;
+use chip8_emu::{
+    frame, state, Audio, Chip8, DisplaySettings, GifRecorder, Movie, Palette, Playback, Quirks, SpeedSettings, Tone,
+    WavAudio, Waveform,
+};
+
+const USAGE: &str = "\
+usage: chip8-headless <rom.ch8> [options]
+  --frames N          run for N frames (default 600), stopping early on halt
+  --seed N            RNG seed (default 0)
+  --quirks NAME       vip, chip48, schip, xochip or modern
+  --xo                enable XO-CHIP
+  --ips N             instructions per second (default 540)
+  --key F:K[:N]       press hex key K at frame F for N frames (default 1)
+  --movie FILE        replay a recorded movie, overriding seed, quirks and IPS
+  --png FILE          write the final framebuffer as PNG
+  --gif FILE          record every frame into an animated GIF
+  --scale N           PNG pixel size, GIF hi-res pixel size (default 1)
+  --palette NAME      PNG and GIF colours: classic, green, amber or lcd
+  --wav FILE          record the beeper into a WAV file
+  --waveform NAME     beeper waveform: square (default) or sine
+  --frequency HZ      beeper pitch, 20 to 20000 (default 440)
+  --volume V          beeper volume, 0 to 1 (default 0.25)
+  --ascii FILE        write the final framebuffer as text, - for stdout
+  --hash              print a hash of the final framebuffer
+  --state FILE        write a JSON dump of the final machine state, - for stdout";
+
+// Runs a ROM without a window or sound device, for CI and scripted tests.
+// Exits with 1 if the machine faults; the outputs are written either way.
+fn main() -> io::Result<()> {
+    let mut rom_path: Option<String> = None;
+    let mut frames: u64 = 600;
+    let mut seed: u64 = 0;
+    let mut quirks: Option<Quirks> = None;
+    let mut xo_chip = false;
+    let mut ips: Option<u32> = None;
+    let mut keys: Vec<KeyEvent> = Vec::new();
+    let mut movie_path: Option<String> = None;
+    let mut png: Option<String> = None;
+    let mut gif: Option<String> = None;
+    let mut scale: usize = 1;
+    let mut palette = Palette::CLASSIC;
+    let mut wav: Option<String> = None;
+    let mut tone = Tone::default();
+    let mut ascii: Option<String> = None;
+    let mut hash = false;
+    let mut state_path: Option<String> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--frames" => frames = parse_value(&arg, args.next())?,
+            "--seed" => seed = parse_value(&arg, args.next())?,
+            "--quirks" => {
+                let name: String = parse_value(&arg, args.next())?;
+                quirks = Some(Quirks::from_name(&name).ok_or_else(|| invalid_input(format!(
+                    "unknown quirks preset {:?}, expected vip, chip48, schip, xochip or modern",
+                    name,
+                )))?);
+            },
+            "--xo" => xo_chip = true,
+            "--ips" => {
+                let value: u32 = parse_value(&arg, args.next())?;
+                if value == 0 || value > SpeedSettings::MAX_IPS {
+                    let max = SpeedSettings::MAX_IPS;
+                    return Err(invalid_input(format!("--ips expects 1 to {}, got {}", max, value)));
+                }
+                ips = Some(value);
+            },
+            "--key" => {
+                let spec: String = parse_value(&arg, args.next())?;
+                keys.extend_from_slice(&parse_key(&spec)
+                    .ok_or_else(|| invalid_input(format!("bad key press {:?}, expected FRAME:KEY[:FRAMES]", spec)))?);
+            },
+            "--movie" => movie_path = Some(parse_value(&arg, args.next())?),
+            "--png" => png = Some(parse_value(&arg, args.next())?),
+            "--gif" => gif = Some(parse_value(&arg, args.next())?),
+            "--scale" => {
+                let value: usize = parse_value(&arg, args.next())?;
+                let max = DisplaySettings::MAX_SCALE as usize;
+                if value == 0 || value > max {
+                    return Err(invalid_input(format!("--scale expects 1 to {}, got {}", max, value)));
+                }
+                scale = value;
+            },
+            "--palette" => {
+                let name: String = parse_value(&arg, args.next())?;
+                palette = Palette::from_name(&name).ok_or_else(|| invalid_input(format!(
+                    "unknown palette {:?}, expected classic, green, amber or lcd",
+                    name,
+                )))?;
+            },
+            "--wav" => wav = Some(parse_value(&arg, args.next())?),
+            "--waveform" => {
+                let name: String = parse_value(&arg, args.next())?;
+                tone.waveform = Waveform::from_name(&name)
+                    .ok_or_else(|| invalid_input(format!("unknown waveform {:?}, expected square or sine", name)))?;
+            },
+            "--frequency" => {
+                tone.frequency = parse_value(&arg, args.next())?;
+                if !(Tone::MIN_FREQUENCY..=Tone::MAX_FREQUENCY).contains(&tone.frequency) {
+                    return Err(invalid_input(format!("--frequency expects 20 to 20000 Hz, got {}", tone.frequency)));
+                }
+            },
+            "--volume" => {
+                tone.volume = parse_value(&arg, args.next())?;
+                if !(0.0..=1.0).contains(&tone.volume) {
+                    return Err(invalid_input(format!("--volume expects 0 to 1, got {}", tone.volume)));
+                }
+            },
+            "--ascii" => ascii = Some(parse_value(&arg, args.next())?),
+            "--hash" => hash = true,
+            "--state" => state_path = Some(parse_value(&arg, args.next())?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ if rom_path.is_none() && !arg.starts_with("--") => rom_path = Some(arg),
+            _ => return Err(invalid_input(format!("unexpected argument {:?}\n{}", arg, USAGE))),
+        }
+    }
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        },
+    };
+    let rom = fs::read(Path::new(&rom_path))?;
+
+    // Scripted key presses are played back as a movie of their own.
+    let (mut chip8, mut movie) = match &movie_path {
+        Some(path) => {
+            let movie = Movie::parse(&fs::read_to_string(path)?).map_err(invalid_data)?;
+            (movie.start(&rom).map_err(invalid_data)?, movie)
+        },
+        None => {
+            let mut chip8 = Chip8::with_seed(&rom, seed);
+            if xo_chip {
+                chip8.set_xo_chip(true);
+            }
+            let default_quirks = if xo_chip { Quirks::XO_CHIP } else { Quirks::default() };
+            chip8.set_quirks(quirks.unwrap_or(default_quirks));
+            if let Some(ips) = ips {
+                chip8.set_instructions_per_second(ips);
+            }
+            let movie = Movie::new(&rom, &chip8).map_err(invalid_data)?;
+            (chip8, movie)
+        },
+    };
+    movie.events.extend_from_slice(&keys);
+    movie.events.sort_by_key(|event| event.frame);
+    let mut playback = Playback::new(&movie);
+
+    let mut recorder = match &gif {
+        Some(path) => Some(GifRecorder::new(io::BufWriter::new(File::create(path)?), &palette, scale)?),
+        None => None,
+    };
+    let mut audio = match &wav {
+        Some(path) => Some(Audio::new(tone, Box::new(WavAudio::create(path)?))),
+        None => None,
+    };
+    let mut result = Ok(());
+    for _ in 0..frames {
+        if chip8.is_halted() {
+            break;
+        }
+        playback.apply(&mut chip8);
+        result = chip8.step_frame();
+        if let Some(recorder) = &mut recorder {
+            recorder.add_frame(&chip8)?;
+        }
+        if let Some(audio) = &mut audio {
+            audio.update(&chip8)?;
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish()?.flush()?;
+    }
+    if let Some(audio) = &mut audio {
+        audio.finish()?;
+    }
+
+    if let Some(path) = &png {
+        frame::write_png(&chip8, &palette, io::BufWriter::new(File::create(path)?), scale)?;
+    }
+    if let Some(path) = &ascii {
+        write_output(path, &frame::ascii(&chip8))?;
+    }
+    if hash {
+        println!("{:016x}", frame::hash(&chip8));
+    }
+    if let Some(path) = &state_path {
+        write_output(path, &state::to_json(&chip8))?;
+    }
+
+    if let Err(err) = result {
+        eprintln!("{}: {}", rom_path, err);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// F:K[:N] presses key K at frame F and releases it N frames later.
+fn parse_key(spec: &str) -> Option<[KeyEvent; 2]> {
+    let mut fields = spec.split(':');
+    let frame: u64 = fields.next()?.parse().ok()?;
+    let key = u8::from_str_radix(fields.next()?, 16).ok().filter(|&key| key < 0x10)?;
+    let duration: u64 = match fields.next() {
+        Some(duration) => duration.parse().ok().filter(|&duration| duration > 0)?,
+        None => 1,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some([
+        KeyEvent { frame, key, pressed: true },
+        KeyEvent { frame: frame.checked_add(duration)?, key, pressed: false },
+    ])
+}
+
+fn write_output(path: &str, text: &str) -> io::Result<()> {
+    if path == "-" {
+        io::stdout().write_all(text.as_bytes())
+    } else {
+        fs::write(path, text)
+    }
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> io::Result<T> {
+    match value.as_ref().map(|value| value.parse()) {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} expects a value, got {:?}", flag, value),
+        )),
+    }
+}
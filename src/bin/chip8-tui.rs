@@ -0,0 +1,474 @@
+use std::io;
+use std::io::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+use std::env;
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, style, terminal, QueueableCommand};
+
+use chip8_emu::{
+    frame, Action, Chip8, Chip8Error, Config, DisplaySettings, GifRecorder, Keymap, Quirks, Speed, SpeedSettings,
+};
+
+const USAGE: &str = "\
+usage: chip8-tui <rom.ch8> [options]
+  --seed N            RNG seed (random by default)
+  --quirks NAME       vip, chip48, schip, xochip or modern
+  --xo                enable XO-CHIP
+  --ips N             instructions per second (default 540)
+  --braille           draw lo-res games with braille too, at half the size
+  --config FILE       keymap settings, by default ~/.config/chip8/config.toml
+
+Keys 1234/QWER/ASDF/ZXCV are the keypad, Space pauses, P steps while paused,
+Esc quits, F12 saves a screenshot and F11 starts and stops a GIF recording.
+Page Up and Page Down change the speed, M toggles slow motion and Tab
+fast-forwards while held, or toggles it in terminals without key releases.";
+
+// Most terminals only report key presses, repeated while the key is held.
+// A pressed key is released when no repeat arrives in time; the first
+// repeat comes after the keyboard's autorepeat delay, so that wait is longer.
+const HOLD_FRAMES: u64 = 30;
+const REPEAT_FRAMES: u64 = 4;
+
+// Terminal frontend for sessions without a display, e.g. over SSH. Drives
+// the core frame by frame like the piston frontend.
+fn main() -> io::Result<()> {
+    let mut rom_path: Option<String> = None;
+    let mut seed: Option<u64> = None;
+    let mut quirks: Option<Quirks> = None;
+    let mut xo_chip = false;
+    let mut ips: Option<u32> = None;
+    let mut braille = false;
+    let mut config_path: Option<String> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--seed" => seed = Some(parse_value(&arg, args.next())?),
+            "--quirks" => {
+                let name: String = parse_value(&arg, args.next())?;
+                quirks = Some(Quirks::from_name(&name).ok_or_else(|| invalid_input(format!(
+                    "unknown quirks preset {:?}, expected vip, chip48, schip, xochip or modern",
+                    name,
+                )))?);
+            },
+            "--xo" => xo_chip = true,
+            "--ips" => {
+                let value: u32 = parse_value(&arg, args.next())?;
+                if value == 0 || value > SpeedSettings::MAX_IPS {
+                    let max = SpeedSettings::MAX_IPS;
+                    return Err(invalid_input(format!("--ips expects 1 to {}, got {}", max, value)));
+                }
+                ips = Some(value);
+            },
+            "--braille" => braille = true,
+            "--config" => config_path = Some(parse_value(&arg, args.next())?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ if rom_path.is_none() && !arg.starts_with("--") => rom_path = Some(arg),
+            _ => return Err(invalid_input(format!("unexpected argument {:?}\n{}", arg, USAGE))),
+        }
+    }
+    let rom_path = match rom_path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        },
+    };
+    let rom = fs::read(Path::new(&rom_path))?;
+
+    let mut chip8 = match seed {
+        Some(seed) => Chip8::with_seed(&rom, seed),
+        None => Chip8::new(&rom),
+    };
+    if xo_chip {
+        chip8.set_xo_chip(true);
+    }
+    let default_quirks = if xo_chip { Quirks::XO_CHIP } else { Quirks::default() };
+    chip8.set_quirks(quirks.unwrap_or(default_quirks));
+
+    // An explicit config file has to exist, the default one is optional.
+    let config = match config_path.map(PathBuf::from).or_else(|| Config::default_path().filter(|path| path.exists())) {
+        Some(path) => {
+            let text = fs::read_to_string(&path)?;
+            Config::parse(&text).map_err(|err| invalid_input(format!("{}: {}", path.display(), err)))?
+        },
+        None => Config::default(),
+    };
+    let rom_name = Path::new(&rom_path).file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let mut keymap = config.keymap(&rom_name, &rom);
+    // Terminals only report characters, not where the key is.
+    if keymap.uses_scancodes() {
+        eprintln!("Scancodes aren't available in a terminal, using the default keys");
+        keymap.set_scancodes(false);
+    }
+
+    let mut speed = config.speed(&rom_name, &rom);
+    speed.ips = ips.unwrap_or(speed.ips);
+    chip8.set_instructions_per_second(speed.ips);
+
+    let mut tui = Tui::new(chip8, Path::new(&rom_path), braille, keymap, config.display);
+    tui.speed = Speed::new(speed);
+    let result = Terminal::enter().and_then(|terminal| tui.run(&terminal));
+    if tui.gif.is_some() {
+        tui.toggle_gif();
+        println!("{}", tui.message);
+    }
+    if let Some(seed) = tui.chip8.rng_seed() {
+        println!("RNG seed: {}", seed);
+    }
+    match result? {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            eprintln!("{}: {}", rom_path, err);
+            std::process::exit(1);
+        },
+    }
+}
+
+// Raw mode on the alternate screen, restored when dropped so a fault or
+// panic doesn't leave the shell unusable.
+struct Terminal {
+    // Whether the terminal reports key releases.
+    releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        stdout.queue(terminal::EnterAlternateScreen)?.queue(cursor::Hide)?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            stdout.queue(PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        stdout.flush()?;
+        Ok(Terminal { releases })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        if self.releases {
+            stdout.queue(PopKeyboardEnhancementFlags).ok();
+        }
+        stdout.queue(cursor::Show).ok();
+        stdout.queue(terminal::LeaveAlternateScreen).ok();
+        stdout.flush().ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+struct Tui {
+    chip8: Chip8,
+    rom_path: PathBuf,
+    braille: bool,
+    keymap: Keymap,
+    // Palette and scale of screenshots and GIFs, which are saved next to the
+    // ROM like in the window frontend.
+    capture: DisplaySettings,
+    gif: Option<(PathBuf, GifRecorder<io::BufWriter<fs::File>>)>,
+    // Shown in the status line.
+    message: String,
+    pause: bool,
+    quit: bool,
+    speed: Speed,
+    // Frame at which each emulated key is released, see `HOLD_FRAMES`.
+    held: [Option<u64>; 16],
+    // The bell rings once when the sound timer starts.
+    beeping: bool,
+    screen: String,
+}
+
+impl Tui {
+    fn new(chip8: Chip8, rom_path: &Path, braille: bool, keymap: Keymap, capture: DisplaySettings) -> Tui {
+        Tui {
+            chip8,
+            rom_path: rom_path.to_path_buf(),
+            braille,
+            keymap,
+            capture,
+            gif: None,
+            message: String::new(),
+            pause: false,
+            quit: false,
+            speed: Speed::default(),
+            held: [None; 16],
+            beeping: false,
+            screen: String::new(),
+        }
+    }
+
+    // The outer result is a terminal error, the inner one a machine fault.
+    fn run(&mut self, terminal: &Terminal) -> io::Result<Result<(), Chip8Error>> {
+        let mut next_frame = Instant::now();
+        self.draw()?;
+        while !self.quit && !self.chip8.is_halted() {
+            if event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+                if let Event::Key(key) = event::read()? {
+                    if let Err(err) = self.key(key, terminal.releases) {
+                        return Ok(Err(err));
+                    }
+                }
+                continue;
+            }
+            // Unlimited fast-forward only stops for input.
+            match self.speed.frame_duration() {
+                Some(duration) => next_frame += duration,
+                None => next_frame = Instant::now(),
+            }
+            if self.pause {
+                continue;
+            }
+            self.release_keys();
+            if let Err(err) = self.chip8.step_frame() {
+                return Ok(Err(err));
+            }
+            self.record_gif_frame();
+            self.beep()?;
+            if self.chip8.take_display_dirty() {
+                self.draw()?;
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    fn key(&mut self, event: KeyEvent, releases: bool) -> Result<(), Chip8Error> {
+        let pressed = event.kind != KeyEventKind::Release;
+        // Raw mode swallows the interrupt signal.
+        if event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return Ok(());
+        }
+        let action = match key_name(event.code) {
+            Some(name) => self.keymap.action(&name),
+            None => None,
+        };
+        match action {
+            Some(Action::Keypad(key)) => {
+                let held = self.held[usize::from(key)];
+                self.held[usize::from(key)] = match (pressed, releases, held) {
+                    (false, _, _) => None,
+                    (true, true, _) => Some(u64::MAX),
+                    (true, false, Some(_)) => Some(self.chip8.frames() + REPEAT_FRAMES),
+                    (true, false, None) => Some(self.chip8.frames() + HOLD_FRAMES),
+                };
+                // Repeats mustn't count as new presses for Fx0A.
+                if held.is_some() != pressed {
+                    self.chip8.set_key(key, pressed);
+                }
+            },
+            Some(Action::FastForward) if releases => self.set_fast_forward(pressed),
+            _ if !pressed => {},
+            Some(Action::FastForward) => {
+                let fast_forward = !self.speed.is_fast_forward();
+                self.set_fast_forward(fast_forward);
+            },
+            Some(Action::Faster) => {
+                Speed::faster(&mut self.chip8);
+                self.screen.clear();
+                self.draw().ok();
+            },
+            Some(Action::Slower) => {
+                Speed::slower(&mut self.chip8);
+                self.screen.clear();
+                self.draw().ok();
+            },
+            Some(Action::SlowMotion) => {
+                let slow_motion = !self.speed.is_slow_motion();
+                self.speed.set_slow_motion(slow_motion);
+                self.screen.clear();
+                self.draw().ok();
+            },
+            Some(Action::Pause) => {
+                self.pause = !self.pause;
+                self.screen.clear();
+                self.draw().ok();
+            },
+            Some(Action::Step) if self.pause && !self.chip8.is_waiting() => {
+                self.chip8.step()?;
+                self.draw().ok();
+            },
+            Some(Action::Quit) => self.quit = true,
+            Some(Action::Screenshot) => {
+                self.screenshot();
+                self.screen.clear();
+                self.draw().ok();
+            },
+            Some(Action::RecordGif) => {
+                self.toggle_gif();
+                self.screen.clear();
+                self.draw().ok();
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn set_fast_forward(&mut self, fast_forward: bool) {
+        if self.speed.is_fast_forward() != fast_forward {
+            self.speed.set_fast_forward(fast_forward);
+            self.screen.clear();
+            self.draw().ok();
+        }
+    }
+
+    fn capture_path(&self, extension: &str) -> PathBuf {
+        self.rom_path.with_extension(format!("{}.{}", self.chip8.frames(), extension))
+    }
+
+    fn screenshot(&mut self) {
+        let path = self.capture_path("png");
+        let scale = self.capture.scale as usize;
+        let result = fs::File::create(&path)
+            .and_then(|file| frame::write_png(&self.chip8, &self.capture.palette, io::BufWriter::new(file), scale));
+        self.message = match result {
+            Ok(()) => format!("saved {}", path.display()),
+            Err(err) => format!("can't save {}: {}", path.display(), err),
+        };
+    }
+
+    fn toggle_gif(&mut self) {
+        if let Some((path, recorder)) = self.gif.take() {
+            let frames = recorder.frames();
+            self.message = match recorder.finish().and_then(|mut output| output.flush()) {
+                Ok(()) => format!("saved {} frames to {}", frames, path.display()),
+                Err(err) => format!("can't save {}: {}", path.display(), err),
+            };
+            return;
+        }
+        let path = self.capture_path("gif");
+        // The GIF canvas is sized for hi-res, half the lo-res scale keeps
+        // its size.
+        let scale = (self.capture.scale as usize / 2).max(1);
+        let recorder = fs::File::create(&path)
+            .and_then(|file| GifRecorder::new(io::BufWriter::new(file), &self.capture.palette, scale));
+        self.message = match recorder {
+            Ok(recorder) => {
+                let message = format!("recording {}", path.display());
+                self.gif = Some((path, recorder));
+                message
+            },
+            Err(err) => format!("can't record {}: {}", path.display(), err),
+        };
+    }
+
+    fn record_gif_frame(&mut self) {
+        let result = match &mut self.gif {
+            Some((_, recorder)) => recorder.add_frame(&self.chip8),
+            None => return,
+        };
+        if let Err(err) = result {
+            if let Some((path, _)) = self.gif.take() {
+                self.message = format!("can't record {}: {}", path.display(), err);
+                self.screen.clear();
+            }
+        }
+    }
+
+    fn release_keys(&mut self) {
+        let frames = self.chip8.frames();
+        for key in 0..16 {
+            if let Some(release) = self.held[usize::from(key)] {
+                if release <= frames {
+                    self.held[usize::from(key)] = None;
+                    self.chip8.set_key(key, false);
+                }
+            }
+        }
+    }
+
+    fn beep(&mut self) -> io::Result<()> {
+        let beeping = self.chip8.sound() > 0;
+        if beeping && !self.beeping {
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+        }
+        self.beeping = beeping;
+        Ok(())
+    }
+
+    // Redraws only when the picture changed.
+    fn draw(&mut self) -> io::Result<()> {
+        let screen = if self.braille || self.chip8.is_hires() {
+            frame::braille(&self.chip8)
+        } else {
+            frame::half_blocks(&self.chip8)
+        };
+        if screen == self.screen {
+            return Ok(());
+        }
+        let mut stdout = io::stdout();
+        if screen.len() != self.screen.len() {
+            stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+        }
+        let mut rows = 0;
+        for (y, line) in screen.lines().enumerate() {
+            stdout.queue(cursor::MoveTo(0, y as u16))?.queue(style::Print(line))?;
+            rows += 1;
+        }
+        let key = |action| self.keymap.keys(action).first().copied().unwrap_or("unbound");
+        let status = if self.pause {
+            format!("paused, {} resumes, {} steps  {}", key(Action::Pause), key(Action::Step), self.message)
+        } else {
+            let speed = self.speed.describe(&self.chip8);
+            format!("{} pauses, {} quits  {}  {}", key(Action::Pause), key(Action::Quit), speed, self.message)
+        };
+        stdout
+            .queue(cursor::MoveTo(0, rows))?
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .queue(style::Print(status))?;
+        stdout.flush()?;
+        self.screen = screen;
+        Ok(())
+    }
+}
+
+// Keymap name of a key, matching the names of the piston frontend.
+fn key_name(code: KeyCode) -> Option<String> {
+    Some(match code {
+        KeyCode::Char(' ') => String::from("space"),
+        KeyCode::Char(c) => c.to_lowercase().to_string(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Esc => String::from("escape"),
+        KeyCode::Enter => String::from("return"),
+        KeyCode::Left => String::from("left"),
+        KeyCode::Right => String::from("right"),
+        KeyCode::Up => String::from("up"),
+        KeyCode::Down => String::from("down"),
+        KeyCode::Backspace => String::from("backspace"),
+        KeyCode::Tab => String::from("tab"),
+        KeyCode::Home => String::from("home"),
+        KeyCode::End => String::from("end"),
+        KeyCode::PageUp => String::from("pageup"),
+        KeyCode::PageDown => String::from("pagedown"),
+        KeyCode::Insert => String::from("insert"),
+        KeyCode::Delete => String::from("delete"),
+        _ => return None,
+    })
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> io::Result<T> {
+    match value.as_ref().map(|value| value.parse()) {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} expects a value, got {:?}", flag, value),
+        )),
+    }
+}
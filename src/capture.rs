@@ -0,0 +1,73 @@
+use std::io;
+use std::io::prelude::*;
+
+use crate::chip8::Chip8;
+use crate::palette::Palette;
+
+// Records the display into an animated GIF at 60 frames per second, one
+// frame per emulated frame. GIF delays are whole centiseconds, so they
+// alternate between 1 and 2 to keep the average right, and runs of
+// identical frames are stored once with the delay of the whole run.
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: usize,
+    height: usize,
+    // The last distinct frame and the frame number it started at. It's only
+    // written when the picture changes and its delay is known.
+    pending: Option<(Vec<u8>, u64)>,
+    frames: u64,
+}
+
+impl<W: Write> GifRecorder<W> {
+    // The canvas fits the hi-res display with `scale` pixels per hi-res
+    // pixel, lo-res frames are drawn at twice that size.
+    pub fn new(output: W, palette: &Palette, scale: usize) -> io::Result<GifRecorder<W>> {
+        let scale = scale.max(1);
+        let (width, height) = (Chip8::HIRES_WIDTH * scale, Chip8::HIRES_HEIGHT * scale);
+        let colors: Vec<u8> = palette.colors.iter().flatten().copied().collect();
+        let mut encoder = gif::Encoder::new(output, width as u16, height as u16, &colors)
+            .map_err(io::Error::other)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+        Ok(GifRecorder { encoder, width, height, pending: None, frames: 0 })
+    }
+
+    // Number of frames recorded so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn add_frame(&mut self, chip8: &Chip8) -> io::Result<()> {
+        let (width, height) = (chip8.width(), chip8.height());
+        let display = chip8.display();
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            let row = &display[y * height / self.height];
+            pixels.extend((0..self.width).map(|x| row[x * width / self.width] & 0b11));
+        }
+        let unchanged = matches!(&self.pending, Some((pending, _)) if *pending == pixels);
+        if !unchanged {
+            self.flush_pending()?;
+            self.pending = Some((pixels, self.frames));
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Writes the last frame and the GIF trailer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_pending()?;
+        self.encoder.into_inner()
+    }
+
+    fn flush_pending(&mut self) -> io::Result<()> {
+        let (pixels, start) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        // Centiseconds at the start of frame `n`, rounded.
+        let time = |n: u64| (n * 100 + 30) / 60;
+        let mut frame = gif::Frame::from_indexed_pixels(self.width as u16, self.height as u16, pixels, None);
+        frame.delay = (time(self.frames) - time(start)).min(u64::from(u16::MAX)) as u16;
+        self.encoder.write_frame(&frame).map_err(io::Error::other)
+    }
+}
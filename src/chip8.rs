@@ -1,39 +1,71 @@
 extern crate rand;
-extern crate find_folder;
-extern crate piston;
-extern crate piston_window;
-extern crate graphics;
-extern crate opengl_graphics;
-extern crate rodio;
-
-use std::time::{Duration, SystemTime};
-use opengl_graphics::{ GlGraphics, OpenGL };
-use piston_window::*;
-use rand::Rng;
+
+use rand::RngCore;
+use crate::error::Chip8Error;
+use crate::instruction::{decode_at, Instruction};
+use crate::quirks::Quirks;
+use crate::rng::{Chip8Rng, RngSource};
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::trace::{TraceRecord, Tracer};
 
 pub struct Chip8 {
-    memory: [u8; 0x1000],
+    // 4 KiB, or 64 KiB in XO-CHIP mode.
+    memory: Vec<u8>,
     v: [u8; 16],
     i: u16,
     delay: u8,
     sound: u8,
+    // Whether the sound timer ran at some point of the current frame, and
+    // of the last completed one.
+    sounding: bool,
+    sounded: bool,
     pc: u16,
     sp: u16,
     stack: [u16; 16],
     halt: bool,
-    display: [[u8; 64]; 32],
-    next_clock: SystemTime,
-    next_timer: SystemTime,
-    clock_duration: Duration,
-    timer_duration: Duration,
-    pause: bool,
+    // Sized for the SCHIP hi-res mode, only the top-left 64x32 pixels are
+    // used in lo-res mode. Each pixel holds one bit per XO-CHIP plane.
+    display: [[u8; Chip8::HIRES_WIDTH]; Chip8::HIRES_HEIGHT],
+    hires: bool,
+    // Set whenever the display changes, cleared by `take_display_dirty`.
+    display_dirty: bool,
     keys: [bool; 16],
     is_waiting: bool,
     waiting_register: usize,
+    rng: RngSource,
+    cycles: u64,
+    frames: u64,
+    frame_cycle: u32,
+    instructions_per_second: u32,
+    quirks: Quirks,
+    vblank_wait: bool,
+    rpl: [u8; 16],
+    xo_chip: bool,
+    plane: u8,
+    audio_pattern: [u8; 16],
+    pitch: u8,
+    // Not part of the machine state, save states leave it alone.
+    tracer: Option<Tracer>,
 }
 
 impl Chip8 {
-    const DEBUG_MODE: bool = false;
+    // 9 instructions per frame at 60 frames per second.
+    pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = 540;
+    // Timer ticks per second of virtual time.
+    pub const FRAME_RATE: u32 = 60;
+    pub const WIDTH: usize = 64;
+    pub const HEIGHT: usize = 32;
+    pub const HIRES_WIDTH: usize = 128;
+    pub const HIRES_HEIGHT: usize = 64;
+    const BIG_FONT_START: usize = 0x50;
+    pub const MEMORY_SIZE: usize = 0x1000;
+    pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+    // Square wave played until an XO-CHIP program loads its own pattern.
+    pub const DEFAULT_AUDIO_PATTERN: [u8; 16] = [0xf0; 16];
+    // Pitch register value for a playback rate of 4000 bits per second.
+    pub const DEFAULT_PITCH: u8 = 64;
+    // Address where programs are loaded and execution starts.
+    pub const PROGRAM_START: usize = 0x200;
     const FONT: [u8; 80] = [
         // 0
         0b11110000,
@@ -132,827 +164,1318 @@ impl Chip8 {
         0b10000000,
         0b10000000
     ];
+    // 8x10 SUPER-CHIP digits, loaded right after the small font.
+    const BIG_FONT: [u8; 160] = [
+        // 0
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        // 1
+        0b00011000,
+        0b01111000,
+        0b01111000,
+        0b00011000,
+        0b00011000,
+        0b00011000,
+        0b00011000,
+        0b00011000,
+        0b11111111,
+        0b11111111,
+        // 2
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        // 3
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b11111111,
+        0b11111111,
+        // 4
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b00000011,
+        0b00000011,
+        // 5
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b11111111,
+        0b11111111,
+        // 6
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        // 7
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b00000110,
+        0b00001100,
+        0b00011000,
+        0b00011000,
+        0b00011000,
+        0b00011000,
+        // 8
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        // 9
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        0b00000011,
+        0b00000011,
+        0b11111111,
+        0b11111111,
+        // A
+        0b01111110,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11111111,
+        0b11111111,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        // B
+        0b11111100,
+        0b11111100,
+        0b11000011,
+        0b11000011,
+        0b11111100,
+        0b11111100,
+        0b11000011,
+        0b11000011,
+        0b11111100,
+        0b11111100,
+        // C
+        0b00111100,
+        0b11111111,
+        0b11000011,
+        0b11000000,
+        0b11000000,
+        0b11000000,
+        0b11000000,
+        0b11000011,
+        0b11111111,
+        0b00111100,
+        // D
+        0b11111100,
+        0b11111110,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11000011,
+        0b11111110,
+        0b11111100,
+        // E
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        // F
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11111111,
+        0b11111111,
+        0b11000000,
+        0b11000000,
+        0b11000000,
+        0b11000000
+    ];
     
-    pub fn new(buffer: &Vec<u8>) -> Chip8 {
-        let mut memory = [0; 0x1000];
-        for i in 0..buffer.len() {
-            memory[i+0x200] = buffer[i];
-        }
+    // Creates a machine with a freshly drawn RNG seed, see `rng_seed`.
+    pub fn new(buffer: &[u8]) -> Chip8 {
+        Chip8::with_rng_source(buffer, RngSource::seeded(rand::random()))
+    }
+
+    // Creates a machine whose RND results are fully determined by `seed`.
+    pub fn with_seed(buffer: &[u8], seed: u64) -> Chip8 {
+        Chip8::with_rng_source(buffer, RngSource::seeded(seed))
+    }
+
+    // Creates a machine drawing RND results from a user-supplied generator.
+    pub fn with_rng(buffer: &[u8], rng: Box<dyn RngCore + Send>) -> Chip8 {
+        Chip8::with_rng_source(buffer, RngSource::Custom(rng))
+    }
+
+    // ROMs too large for 4 KiB can only be XO-CHIP programs, they get the
+    // full 64 KiB from the start. Anything past 64 KiB is dropped.
+    fn with_rng_source(buffer: &[u8], rng: RngSource) -> Chip8 {
+        let size = if Chip8::PROGRAM_START + buffer.len() <= Chip8::MEMORY_SIZE {
+            Chip8::MEMORY_SIZE
+        } else {
+            Chip8::XO_CHIP_MEMORY_SIZE
+        };
+        let mut memory = vec![0; size];
+        let len = buffer.len().min(size - Chip8::PROGRAM_START);
+        memory[Chip8::PROGRAM_START..Chip8::PROGRAM_START + len].copy_from_slice(&buffer[..len]);
         let mut new_chip8 = Chip8 {
             memory,
             v: [0; 16], i: 0,
             delay: 0, sound: 0,
-            pc: 0x200,
+            sounding: false, sounded: false,
+            pc: Chip8::PROGRAM_START as u16,
             sp: 0, stack: [0; 16],
             halt: false,
-            display: [[0; 64]; 32],
-            next_clock: SystemTime::now(),
-            next_timer: SystemTime::now(),
-            clock_duration: Duration::new(0, 181852), // ~540 Hz
-            timer_duration: Duration::new(0, 16666667), // ~60 Hz
-            pause: false,
+            display: [[0; Chip8::HIRES_WIDTH]; Chip8::HIRES_HEIGHT],
+            hires: false,
+            display_dirty: true,
             keys: [false; 16],
             is_waiting: false,
             waiting_register: 0,
+            rng,
+            cycles: 0,
+            frames: 0,
+            frame_cycle: 0,
+            instructions_per_second: Chip8::DEFAULT_INSTRUCTIONS_PER_SECOND,
+            quirks: Quirks::default(),
+            vblank_wait: false,
+            rpl: [0; 16],
+            xo_chip: false,
+            plane: 1,
+            audio_pattern: Chip8::DEFAULT_AUDIO_PATTERN,
+            pitch: Chip8::DEFAULT_PITCH,
+            tracer: None,
         };
         new_chip8.init_font();
         new_chip8
     }
 
-    pub fn clock(&mut self) {
+    // Executes a single instruction. Faults leave the machine at the
+    // offending instruction and are reported to the caller.
+    pub fn clock(&mut self) -> Result<(), Chip8Error> {
         let u_ptr = usize::from(self.pc);
+        if u_ptr + 1 >= self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds { pc: self.pc, opcode: 0, address: u_ptr + 1 });
+        }
         let cur_instruction = u16::from(self.memory[u_ptr]) << 8 | u16::from(self.memory[u_ptr+1]);
         if cur_instruction == 0x0000 {
             self.halt = true;
-            return;
-        }
-        let hbit = cur_instruction >> 12;
-        match hbit {
-            0x0 => {
-                if cur_instruction == 0x00e0 {
-                    self.clear(cur_instruction);
-                } else if cur_instruction == 0x00ee {
-                    self.return_subroutine(cur_instruction);
-                } else {
-                    self.call(cur_instruction);
-                }
-            },
-            0x1 => self.jump(cur_instruction),
-            0x2 => self.call_subroutine(cur_instruction),
-            0x3 => self.skip_eq_xkk(cur_instruction),
-            0x4 => self.skip_ne_xkk(cur_instruction),
-            0x5 if cur_instruction & 0x000f == 0 => self.skip_eq_xy(cur_instruction),
-            0x6 => self.set_vx_kk(cur_instruction),
-            0x7 => self.add_vx_kk(cur_instruction),
-            0x8 => {
-                match cur_instruction & 0x000f {
-                    0x0 => self.set_vx_vy(cur_instruction),
-                    0x1 => self.or_vx_vy(cur_instruction),
-                    0x2 => self.and_vx_vy(cur_instruction),
-                    0x3 => self.xor_vx_vy(cur_instruction),
-                    0x4 => self.add_vx_vy(cur_instruction),
-                    0x5 => self.sub_vx_vy(cur_instruction),
-                    0x6 => self.shr_vx_vy(cur_instruction),
-                    0x7 => self.subn_vx_vy(cur_instruction),
-                    0xE => self.shl_vx_vy(cur_instruction),
-                    _ => panic!("OPERATION NOT SUPPORTED!")
-                }
-            },
-            0x9 => self.skip_ne_xy(cur_instruction),
-            0xA => self.set_i_nnn(cur_instruction),
-            0xB => self.jump_v0(cur_instruction),
-            0xC => self.rnd(cur_instruction),
-            0xD => self.draw(cur_instruction),
-            0xE => {
-                if cur_instruction & 0x00ff == 0x9E {
-                    self.skip_key_pressed(cur_instruction);
-                } else if cur_instruction & 0x00ff == 0xA1 {
-                    self.skip_key_not_pressed(cur_instruction);
-                } else {
-                    panic!("OPERATION NOT SUPPORTED!");
-                }
-            },
-            0xF => {
-                match cur_instruction & 0x00ff {
-                    0x07 => self.set_vx_dt(cur_instruction),
-                    0x0A => self.wait_key(cur_instruction),
-                    0x15 => self.set_dt_vx(cur_instruction),
-                    0x18 => self.set_sound_vx(cur_instruction),
-                    0x1E => self.add_i_vx(cur_instruction),
-                    0x29 => self.load_sprite(cur_instruction),
-                    0x33 => self.bcd(cur_instruction),
-                    0x55 => self.load_v0_vx_i(cur_instruction),
-                    0x65 => self.load_i_v0_vx(cur_instruction),
-                    _ => panic!("OPERATION NOT SUPPORTED!"),
-                }
+            return Ok(());
+        }
+        if cur_instruction == 0xf000 && self.xo_chip {
+            self.check_memory(cur_instruction, u_ptr + 2, 2)?;
+        }
+        let instruction = match decode_at(&self.memory, u_ptr) {
+            Some(instruction) if instruction.is_xo_chip() && !self.xo_chip => None,
+            // Before SUPER-CHIP, 0nnn called machine code at nnn.
+            Some(instruction) if instruction.is_super_chip() && !self.has_super_chip() => {
+                if cur_instruction & 0xf000 == 0 { Some(Instruction::Sys(cur_instruction)) } else { None }
             },
-            _ => panic!("OPERATION NOT SUPPORTED!")
-        }
-    }
-
-    pub fn run(&mut self) {
-        let opengl = OpenGL::V3_2;
-        let mut window: PistonWindow =
-            WindowSettings::new("CHIP8", [640, 320]).graphics_api(opengl)
-            .exit_on_esc(true).build().unwrap();
-        window.set_ups(1000);
-        let ref mut gl = GlGraphics::new(opengl);
-        
-        let assets = find_folder::Search::ParentsThenKids(3, 3).for_folder("assets").unwrap();
-        println!("{:?}", assets);
-        let device = rodio::default_output_device().unwrap();
-        let sink = rodio::Sink::new(&device);
-        let source = rodio::source::SineWave::new(440);
-        sink.pause();
-        sink.append(source);
-
-        while let Some(event) = window.next() {
+            instruction => instruction,
+        };
+        let instruction = match instruction {
+            Some(instruction) => instruction,
+            None => return Err(self.unknown_opcode(cur_instruction)),
+        };
+        let traced = match &self.tracer {
+            Some(tracer) => tracer.wants(self.pc, cur_instruction),
+            None => false,
+        };
+        if !traced {
+            return self.execute(cur_instruction, instruction);
+        }
+        let before = (self.pc, self.v, self.i, self.sp, self.delay, self.sound);
+        let (pc, v_before, i_before, sp_before, delay_before, sound_before) = before;
+        self.execute(cur_instruction, instruction)?;
+        let record = TraceRecord {
+            cycle: self.cycles,
+            pc,
+            opcode: cur_instruction,
+            instruction,
+            v_before,
+            i_before,
+            sp_before,
+            delay_before,
+            sound_before,
+            v_after: self.v,
+            i_after: self.i,
+            pc_after: self.pc,
+            sp_after: self.sp,
+            delay_after: self.delay,
+            sound_after: self.sound,
+        };
+        if let Some(tracer) = &mut self.tracer {
+            tracer.record(&record);
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, opcode: u16, instruction: Instruction) -> Result<(), Chip8Error> {
+        use crate::instruction::Instruction::*;
+
+        match instruction {
+            Sys(_) => self.call(),
+            Cls => self.clear(),
+            Ret => self.return_subroutine(opcode)?,
+            Scd(n) => self.scroll_down(usize::from(n)),
+            Scu(n) => self.scroll_up(usize::from(n)),
+            Scr => self.scroll_right(),
+            Scl => self.scroll_left(),
+            Exit => self.exit(),
+            Low => self.set_resolution(false),
+            High => self.set_resolution(true),
+            Jp(nnn) => self.jump(nnn),
+            Call(nnn) => self.call_subroutine(opcode, nnn)?,
+            SeVxByte { x, kk } => self.skip_eq_xkk(usize::from(x), kk),
+            SneVxByte { x, kk } => self.skip_ne_xkk(usize::from(x), kk),
+            SeVxVy { x, y } => self.skip_eq_xy(usize::from(x), usize::from(y)),
+            Save { x, y } => self.save_range(opcode, usize::from(x), usize::from(y))?,
+            Load { x, y } => self.load_range(opcode, usize::from(x), usize::from(y))?,
+            LdVxByte { x, kk } => self.set_vx_kk(usize::from(x), kk),
+            AddVxByte { x, kk } => self.add_vx_kk(usize::from(x), kk),
+            LdVxVy { x, y } => self.set_vx_vy(usize::from(x), usize::from(y)),
+            Or { x, y } => self.or_vx_vy(usize::from(x), usize::from(y)),
+            And { x, y } => self.and_vx_vy(usize::from(x), usize::from(y)),
+            Xor { x, y } => self.xor_vx_vy(usize::from(x), usize::from(y)),
+            AddVxVy { x, y } => self.add_vx_vy(usize::from(x), usize::from(y)),
+            Sub { x, y } => self.sub_vx_vy(usize::from(x), usize::from(y)),
+            Shr { x, y } => self.shr_vx_vy(usize::from(x), usize::from(y)),
+            Subn { x, y } => self.subn_vx_vy(usize::from(x), usize::from(y)),
+            Shl { x, y } => self.shl_vx_vy(usize::from(x), usize::from(y)),
+            SneVxVy { x, y } => self.skip_ne_xy(usize::from(x), usize::from(y)),
+            LdI(nnn) => self.set_i_nnn(nnn),
+            JpV0(nnn) => self.jump_v0(nnn),
+            Rnd { x, kk } => self.rnd(usize::from(x), kk),
+            Drw { x, y, n } => self.draw(opcode, usize::from(x), usize::from(y), n)?,
+            LdILong(nnnn) => self.set_i_long(nnnn),
+            Plane(n) => self.select_plane(n),
+            Audio => self.load_audio(opcode)?,
+            Pitch { x } => self.set_pitch(usize::from(x)),
+            Skp { x } => self.skip_key_pressed(opcode, usize::from(x))?,
+            Sknp { x } => self.skip_key_not_pressed(opcode, usize::from(x))?,
+            LdVxDt { x } => self.set_vx_dt(usize::from(x)),
+            LdVxK { x } => self.wait_key(usize::from(x)),
+            LdDtVx { x } => self.set_dt_vx(usize::from(x)),
+            LdStVx { x } => self.set_sound_vx(usize::from(x)),
+            AddIVx { x } => self.add_i_vx(usize::from(x)),
+            LdFVx { x } => self.load_sprite(usize::from(x)),
+            LdHfVx { x } => self.load_big_sprite(usize::from(x)),
+            LdBVx { x } => self.bcd(opcode, usize::from(x))?,
+            LdIVx { x } => self.load_v0_vx_i(opcode, usize::from(x))?,
+            LdVxI { x } => self.load_i_v0_vx(opcode, usize::from(x))?,
+            LdRVx { x } => self.save_rpl(usize::from(x)),
+            LdVxR { x } => self.load_rpl(usize::from(x)),
+        }
+        Ok(())
+    }
+
+    // Advances virtual time by one cycle: executes an instruction unless the
+    // machine is halted, blocked on Fx0A or waiting for the display, and
+    // ticks the timers at the end of every frame. Frames without a cycle,
+    // below 60 instructions per second, end before the cycle runs.
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.halt {
+            return Ok(());
+        }
+        while self.frame_cycle >= self.frame_length() {
+            self.end_frame();
+        }
+        if !self.is_waiting && !self.vblank_wait {
+            self.clock()?;
+        }
+        self.cycles += 1;
+        self.frame_cycle += 1;
+        if self.frame_cycle >= self.frame_length() {
+            self.end_frame();
+        }
+        Ok(())
+    }
+
+    // Cycles in the current frame. The instructions per second are spread
+    // over the frames of every second, so the rate is exact even when it
+    // isn't a multiple of 60.
+    fn frame_length(&self) -> u32 {
+        let rate = u64::from(Chip8::FRAME_RATE);
+        let ips = u64::from(self.instructions_per_second);
+        let frame = self.frames % rate;
+        ((frame + 1) * ips / rate - frame * ips / rate) as u32
+    }
+
+    fn end_frame(&mut self) {
+        self.frame_cycle = 0;
+        self.frames += 1;
+        self.vblank_wait = false;
+        self.sounded = self.sounding;
+        self.tick_timers();
+        self.sounding = self.sound > 0;
+    }
+
+    // Runs `n` virtual cycles, stopping early on a fault or halt.
+    pub fn run_cycles(&mut self, n: u64) -> Result<(), Chip8Error> {
+        for _ in 0..n {
             if self.halt {
                 break;
             }
-            let mut button: Option<piston_window::Button> = None;
-            let mut button_pressed: bool = false;
+            self.step()?;
+        }
+        Ok(())
+    }
 
-            if let Some(cur_button) = event.press_args() {
-                button = Some(cur_button);
-                button_pressed = true;
-            }
-            if let Some(cur_button) = event.release_args() {
-                button = Some(cur_button);
-            }
+    // Runs until the end of the current frame, i.e. the next timer tick.
+    pub fn step_frame(&mut self) -> Result<(), Chip8Error> {
+        match self.frame_length().checked_sub(self.frame_cycle) {
+            Some(cycles) if cycles > 0 => self.run_cycles(u64::from(cycles)),
+            _ if self.halt => Ok(()),
+            _ => {
+                self.end_frame();
+                Ok(())
+            },
+        }
+    }
 
-            if button != None {
-                use piston_window::Button::Keyboard;
-                let key = match button.unwrap() {
-                    Keyboard(Key::D1) => 0x1,   //  Real keys |  Chip8
-                    Keyboard(Key::D2) => 0x2,   //  1 2 3 4   |  1 2 3 C
-                    Keyboard(Key::D3) => 0x3,   //  Q W E R   |  4 5 6 D
-                    Keyboard(Key::D4) => 0xC,   //  A S D F   |  7 8 9 E
-                    Keyboard(Key::Q) => 0x4,    //  Z X C V   |  A 0 B F
-                    Keyboard(Key::W) => 0x5,
-                    Keyboard(Key::E) => 0x6,
-                    Keyboard(Key::R) => 0xD,
-                    Keyboard(Key::A) => 0x7,
-                    Keyboard(Key::S) => 0x8,
-                    Keyboard(Key::D) => 0x9,
-                    Keyboard(Key::F) => 0xE,
-                    Keyboard(Key::Z) => 0xA,
-                    Keyboard(Key::X) => 0x0,
-                    Keyboard(Key::C) => 0xB,
-                    Keyboard(Key::V) => 0xF,
-                    _ => 0x10,
-                };
-                if key < 0x10 {
-                    self.keys[key as usize] = button_pressed;
-                    if button_pressed && self.is_waiting {
-                        self.v[self.waiting_register] = key;
-                        self.is_waiting = false;
-                        self.next_clock = SystemTime::now();
-                    }
-                } else if button == Some(Keyboard(Key::Space)) && button_pressed {
-                    self.toggle_pause();
-                } else if button == Some(Keyboard(Key::P)) && !self.is_waiting && button_pressed {
-                    self.clock();
-                }
-            }
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
 
-            if !self.pause && !self.is_waiting && self.next_clock <= SystemTime::now() {
-                self.next_clock += self.clock_duration;
-                self.clock();
-            }
+    // Number of completed frames, i.e. 60 Hz timer ticks.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
 
-            if !self.pause && self.next_timer <= SystemTime::now() {
-                self.next_timer += self.timer_duration;
-                if self.delay > 0 {
-                    self.delay -= 1;
-                }
-                if self.sound > 0 {
-                    self.sound -= 1;
-                }
-            }
-            if self.sound > 0 && sink.is_paused() {
-                sink.play();
-            }
-            if self.sound == 0 && !sink.is_paused() {
-                sink.pause();
-            }
+    pub fn instructions_per_second(&self) -> u32 {
+        self.instructions_per_second
+    }
 
-            if let Some(args) = event.render_args() {
-                // if next_frame <= SystemTime::now() {
-                    // next_frame += frame_timer;
-                    gl.draw(args.viewport(), |context, graphics| {
-                        graphics::clear([0.0, 0.0, 0.0, 1.0], graphics);
-                    
-                        // Displaying the screen
-                        for i in 0..32 {
-                            for j in 0..64 {
-                                if self.display[i][j] != 0 {
-                                    graphics::rectangle(
-                                        [1.0, 1.0, 1.0, 1.0],
-                                        [j as f64 * 10.0, i as f64 * 10.0, 10.0, 10.0],
-                                        context.transform,
-                                        graphics,
-                                    )
-                                }
-                            }
-                        }
-                    });
-                // }
-            }
+    // At least one instruction per second.
+    pub fn set_instructions_per_second(&mut self, instructions_per_second: u32) {
+        self.instructions_per_second = instructions_per_second.max(1);
+    }
+
+    // Decrements the delay and sound timers, called at the end of every
+    // frame by `step`.
+    pub fn tick_timers(&mut self) {
+        if self.delay > 0 {
+            self.delay -= 1;
+        }
+        if self.sound > 0 {
+            self.sound -= 1;
         }
     }
 
-    fn init_font(&mut self) {
-        for i in 0..Chip8::FONT.len() {
-            self.memory[i] = Chip8::FONT[i];
+    // Updates the state of a key on the hex keypad. A press also resumes
+    // execution if the machine is blocked on Fx0A.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let key = key & 0xf;
+        self.keys[usize::from(key)] = pressed;
+        if pressed && self.is_waiting {
+            self.v[self.waiting_register] = key;
+            self.is_waiting = false;
         }
     }
 
-    fn pause(&mut self) {
-        self.pause = true;
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
     }
 
-    fn unpause(&mut self) {
-        self.pause = false;
-        self.next_clock = SystemTime::now();
-        self.next_timer = SystemTime::now();
+    // Direct access for debuggers, writes bypass every check.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
     }
 
-    pub fn toggle_pause(&mut self) {
-        if self.pause {
-            self.unpause();
-        } else {
-            self.pause();
+    pub fn set_v(&mut self, x: usize, value: u8) {
+        self.v[x & 0xf] = value;
+    }
+
+    pub fn set_i(&mut self, i: u16) {
+        self.i = i;
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    // Clamped to the depth of the stack.
+    pub fn set_sp(&mut self, sp: u16) {
+        self.sp = sp.min(self.stack.len() as u16);
+    }
+
+    pub fn set_delay(&mut self, delay: u8) {
+        self.delay = delay;
+    }
+
+    pub fn set_sound(&mut self, sound: u8) {
+        self.sound = sound;
+        self.sounding |= sound > 0;
+    }
+
+    pub fn v(&self) -> &[u8; 16] {
+        &self.v
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    pub fn stack(&self) -> &[u16; 16] {
+        &self.stack
+    }
+
+    pub fn delay(&self) -> u8 {
+        self.delay
+    }
+
+    pub fn sound(&self) -> u8 {
+        self.sound
+    }
+
+    // Whether the buzzer sounded during the last completed frame, i.e. the
+    // sound timer was running at its start or was set during it. A timer set
+    // to N sounds for N frames, the one setting it included.
+    pub fn sounded(&self) -> bool {
+        self.sounded
+    }
+
+    // The framebuffer, indexed as `display()[row][column]`. Only the first
+    // `height()` rows and `width()` columns are visible.
+    pub fn display(&self) -> &[[u8; Chip8::HIRES_WIDTH]; Chip8::HIRES_HEIGHT] {
+        &self.display
+    }
+
+    // Copies the visible pixels row by row into `buffer`, `width()` bytes
+    // per row with the plane bits of each pixel.
+    pub fn framebuffer(&self, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        for row in self.display.iter().take(self.height()) {
+            buffer.extend_from_slice(&row[..self.width()]);
         }
     }
+
+    // Whether CLS, DRW, scrolling, a resolution switch or a loaded state
+    // changed the display since the last call, which resets the flag.
+    // Frontends use it to skip redrawing unchanged frames.
+    pub fn take_display_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.display_dirty, false)
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires { Chip8::HIRES_WIDTH } else { Chip8::WIDTH }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires { Chip8::HIRES_HEIGHT } else { Chip8::HEIGHT }
+    }
+
+    // SCHIP RPL user flags, saved and restored by Fx75/Fx85.
+    pub fn rpl_flags(&self) -> &[u8; 16] {
+        &self.rpl
+    }
+
+    pub fn is_xo_chip(&self) -> bool {
+        self.xo_chip
+    }
+
+    // Whether the SUPER-CHIP instructions are available, see
+    // `Quirks::super_chip`.
+    pub fn has_super_chip(&self) -> bool {
+        self.quirks.super_chip || self.xo_chip
+    }
+
+    // Enables the XO-CHIP instructions and the 64 KiB address space.
+    // Disabling it truncates memory back to 4 KiB.
+    pub fn set_xo_chip(&mut self, enabled: bool) {
+        self.xo_chip = enabled;
+        let size = if enabled { Chip8::XO_CHIP_MEMORY_SIZE } else { Chip8::MEMORY_SIZE };
+        self.memory.resize(size, 0);
+    }
+
+    // Bitmask of the planes drawn to, set by Fn01.
+    pub fn plane(&self) -> u8 {
+        self.plane
+    }
+
+    // 128 one-bit samples played in a loop while the sound timer is active.
+    pub fn audio_pattern(&self) -> &[u8; 16] {
+        &self.audio_pattern
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // Playback rate of the audio pattern in bits per second.
+    pub fn audio_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((f64::from(self.pitch) - 64.0) / 48.0)
+    }
+
+    pub fn keys(&self) -> &[bool; 16] {
+        &self.keys
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halt
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.is_waiting
+    }
+
+    // Set after a sprite is drawn with the display_wait quirk, execution
+    // resumes at the next frame.
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.vblank_wait
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Attaches a tracer that records every executed instruction, `None`
+    // detaches and returns the current one.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Option<Tracer> {
+        std::mem::replace(&mut self.tracer, tracer)
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
+
+    // Seed of the built-in generator, `None` when a custom one is used.
+    pub fn rng_seed(&self) -> Option<u64> {
+        self.rng.seed()
+    }
+
+    pub fn rng(&self) -> &RngSource {
+        &self.rng
+    }
+
+    // Serializes the whole machine. After the header every field follows in
+    // declaration order, little-endian, with memory prefixed by its size.
+    // A custom RNG can't be saved, loading keeps whatever generator the
+    // machine currently has.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.u32(self.memory.len() as u32);
+        w.bytes(&self.memory);
+        w.bytes(&self.v);
+        w.u16(self.i);
+        w.u8(self.delay);
+        w.u8(self.sound);
+        w.bool(self.sounding);
+        w.bool(self.sounded);
+        w.u16(self.pc);
+        w.u16(self.sp);
+        for &addr in &self.stack {
+            w.u16(addr);
+        }
+        w.bool(self.halt);
+        for row in &self.display {
+            w.bytes(row);
+        }
+        w.bool(self.hires);
+        for &pressed in &self.keys {
+            w.bool(pressed);
+        }
+        w.bool(self.is_waiting);
+        w.u8(self.waiting_register as u8);
+        match self.rng {
+            RngSource::Seeded { seed, rng } => {
+                w.u8(0);
+                w.u64(seed);
+                w.u64(rng.state());
+            },
+            RngSource::Custom(_) => w.u8(1),
+        }
+        w.u64(self.cycles);
+        w.u64(self.frames);
+        w.u32(self.frame_cycle);
+        w.u32(self.instructions_per_second);
+        w.u8(self.quirks.to_bits());
+        w.bool(self.vblank_wait);
+        w.bytes(&self.rpl);
+        w.bool(self.xo_chip);
+        w.u8(self.plane);
+        w.bytes(&self.audio_pattern);
+        w.u8(self.pitch);
+        w.finish()
+    }
+
+    // Restores a state produced by `save_state`. Nothing is changed unless
+    // the whole state is valid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut r = StateReader::new(data)?;
+        let memory_size = r.u32()? as usize;
+        if memory_size != Chip8::MEMORY_SIZE && memory_size != Chip8::XO_CHIP_MEMORY_SIZE {
+            return Err(StateError::Invalid { field: "memory size" });
+        }
+        let memory = r.bytes(memory_size)?.to_vec();
+        let v = r.array()?;
+        let i = r.u16()?;
+        let delay = r.u8()?;
+        let sound = r.u8()?;
+        let sounding = r.bool()?;
+        let sounded = r.bool()?;
+        let pc = r.u16()?;
+        let sp = r.u16()?;
+        if usize::from(sp) > self.stack.len() {
+            return Err(StateError::Invalid { field: "stack pointer" });
+        }
+        let mut stack = [0; 16];
+        for addr in &mut stack {
+            *addr = r.u16()?;
+        }
+        let halt = r.bool()?;
+        let mut display = [[0; Chip8::HIRES_WIDTH]; Chip8::HIRES_HEIGHT];
+        for row in &mut display {
+            *row = r.array()?;
+        }
+        let hires = r.bool()?;
+        let mut keys = [false; 16];
+        for pressed in &mut keys {
+            *pressed = r.bool()?;
+        }
+        let is_waiting = r.bool()?;
+        let waiting_register = usize::from(r.u8()?);
+        if waiting_register >= self.v.len() {
+            return Err(StateError::Invalid { field: "waiting register" });
+        }
+        let rng = match r.u8()? {
+            0 => {
+                let seed = r.u64()?;
+                Some(RngSource::Seeded { seed, rng: Chip8Rng::from_state(r.u64()?) })
+            },
+            1 => None,
+            _ => return Err(StateError::Invalid { field: "RNG kind" }),
+        };
+        let cycles = r.u64()?;
+        let frames = r.u64()?;
+        let frame_cycle = r.u32()?;
+        let instructions_per_second = r.u32()?.max(1);
+        let quirks = Quirks::from_bits(r.u8()?);
+        let vblank_wait = r.bool()?;
+        let rpl = r.array()?;
+        let xo_chip = r.bool()?;
+        let plane = r.u8()?;
+        let audio_pattern = r.array()?;
+        let pitch = r.u8()?;
+        r.finish()?;
+
+        self.memory = memory;
+        self.v = v;
+        self.i = i;
+        self.delay = delay;
+        self.sound = sound;
+        self.sounding = sounding;
+        self.sounded = sounded;
+        self.pc = pc;
+        self.sp = sp;
+        self.stack = stack;
+        self.halt = halt;
+        self.display = display;
+        self.hires = hires;
+        self.display_dirty = true;
+        self.keys = keys;
+        self.is_waiting = is_waiting;
+        self.waiting_register = waiting_register;
+        if let Some(rng) = rng {
+            self.rng = rng;
+        }
+        self.cycles = cycles;
+        self.frames = frames;
+        self.frame_cycle = frame_cycle;
+        self.instructions_per_second = instructions_per_second;
+        self.quirks = quirks;
+        self.vblank_wait = vblank_wait;
+        self.rpl = rpl;
+        self.xo_chip = xo_chip;
+        self.plane = plane;
+        self.audio_pattern = audio_pattern;
+        self.pitch = pitch;
+        Ok(())
+    }
+
+    fn unknown_opcode(&self, instruction: u16) -> Chip8Error {
+        Chip8Error::UnknownOpcode { pc: self.pc, opcode: instruction }
+    }
+
+    // Checks that `len` bytes starting at `address` are addressable.
+    fn check_memory(&self, instruction: u16, address: usize, len: usize) -> Result<(), Chip8Error> {
+        if address + len > self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                pc: self.pc,
+                opcode: instruction,
+                address: address.max(self.memory.len()),
+            });
+        }
+        Ok(())
+    }
+
+    // Looks up the key whose number is stored in Vx.
+    fn key_vx(&self, instruction: u16, x: usize) -> Result<bool, Chip8Error> {
+        match self.keys.get(usize::from(self.v[x])) {
+            Some(&pressed) => Ok(pressed),
+            None => Err(Chip8Error::InvalidKey { pc: self.pc, opcode: instruction, key: self.v[x] }),
+        }
+    }
+
+    fn init_font(&mut self) {
+        self.memory[..Chip8::FONT.len()].copy_from_slice(&Chip8::FONT);
+        self.memory[Chip8::BIG_FONT_START..Chip8::BIG_FONT_START + Chip8::BIG_FONT.len()]
+            .copy_from_slice(&Chip8::BIG_FONT);
+    }
 }
 
+
 impl Chip8 {
-    fn call(&mut self, instruction: u16) {
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: CALL", self.pc, instruction);
+    fn call(&mut self) {
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn clear(&mut self) {
+        // Only the selected XO-CHIP planes are cleared.
+        let plane = self.plane;
+        for pixel in self.display.iter_mut().flat_map(|row| row.iter_mut()) {
+            *pixel &= !plane;
         }
-        self.pc += 2;
+        self.display_dirty = true;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn clear(&mut self, instruction: u16) {
-        self.display = [[0; 64]; 32];
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: CLEAR_SCR", self.pc, instruction);
+    // 00Cn - SCD nibble
+    // Scroll the display down by n pixels.
+    fn scroll_down(&mut self, n: usize) {
+        self.scroll(0, n as isize);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // 00Dn - SCU nibble
+    // Scroll the display up by n pixels.
+    fn scroll_up(&mut self, n: usize) {
+        self.scroll(0, -(n as isize));
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // 00FB - SCR
+    // Scroll the display right by 4 pixels.
+    fn scroll_right(&mut self) {
+        self.scroll(4, 0);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // 00FC - SCL
+    // Scroll the display left by 4 pixels.
+    fn scroll_left(&mut self) {
+        self.scroll(-4, 0);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // 00FD - EXIT
+    // Stop the interpreter.
+    fn exit(&mut self) {
+        self.halt = true;
+    }
+
+    // 00FE - LOW, 00FF - HIGH
+    // Switch between the 64x32 and 128x64 display modes, clearing the screen.
+    fn set_resolution(&mut self, hires: bool) {
+        self.hires = hires;
+        self.display = [[0; Chip8::HIRES_WIDTH]; Chip8::HIRES_HEIGHT];
+        self.display_dirty = true;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Moves the selected planes of the visible area by (dx, dy) pixels,
+    // filling the uncovered area with blank pixels.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let plane = self.plane;
+        let old = self.display;
+        for row in 0..height {
+            for column in 0..width {
+                let (src_row, src_column) = (row - dy, column - dx);
+                let shifted = if src_row >= 0 && src_row < height && src_column >= 0 && src_column < width {
+                    old[src_row as usize][src_column as usize]
+                } else {
+                    0
+                };
+                let pixel = &mut self.display[row as usize][column as usize];
+                *pixel = (*pixel & !plane) | (shifted & plane);
+            }
         }
-        self.pc += 2;
+        self.display_dirty = true;
     }
 
-    fn return_subroutine(&mut self, instruction: u16) {
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: RETURN({:04x})", self.pc, instruction, self.stack[usize::from(self.sp-1)]);
+    fn return_subroutine(&mut self, instruction: u16) -> Result<(), Chip8Error> {
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow { pc: self.pc, opcode: instruction });
         }
 
-        self.pc = self.stack[usize::from(self.sp-1)] + 2;
+        self.pc = self.stack[usize::from(self.sp-1)].wrapping_add(2);
         self.sp -= 1;
+        Ok(())
     }
 
-    fn jump(&mut self, instruction: u16) {
-        let nnn = instruction & 0x0fff;
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: JUMP({:04x})", self.pc, instruction, nnn);
-        }
+    fn jump(&mut self, nnn: u16) {
         self.pc = nnn;
     }
 
-    fn call_subroutine(&mut self, instruction: u16) {
-        let nnn = instruction & 0x0fff;
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: CALL_SUB({:04x})", self.pc, instruction, nnn);
+    fn call_subroutine(&mut self, instruction: u16, nnn: u16) -> Result<(), Chip8Error> {
+        if usize::from(self.sp) >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow { pc: self.pc, opcode: instruction });
         }
         self.stack[usize::from(self.sp)] = self.pc;
         self.sp += 1;
         self.pc = nnn;
+        Ok(())
     }
 
-    fn skip_eq_xkk(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let kk = (instruction & 0x00ff) as u8;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SKIP IF(V[{:02x}]({:02x})=={:02x}) -> {}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                kk,
-                self.v[x] == kk
-            );
-        }
+    fn skip_eq_xkk(&mut self, x: usize, kk: u8) {
         if self.v[x] == kk {
-            self.pc += 2;
+            self.skip_next();
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn skip_ne_xkk(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let kk = (instruction & 0x00ff) as u8;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SKIP IF(V[{:02x}]({:02x})!={:02x}) -> {}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                kk,
-                self.v[x] != kk
-            );
-        }
+    fn skip_ne_xkk(&mut self, x: usize, kk: u8) {
         if self.v[x] != kk {
-            self.pc += 2;
-        }
-        self.pc += 2;
-    }
-
-    fn skip_eq_xy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SKIP IF(V[{:02x}]({:02x})==V[{:02x}]({:02x})) -> {}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                self.v[x] == self.v[y]
-            );
+            self.skip_next();
         }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn skip_eq_xy(&mut self, x: usize, y: usize) {
         if self.v[x] == self.v[y] {
-            self.pc += 2;
+            self.skip_next();
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn set_vx_kk(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let kk = (instruction & 0x00ff) as u8;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SET V[{:02x}]({:02x})={:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                kk
-            );
-        }
+    fn set_vx_kk(&mut self, x: usize, kk: u8) {
         self.v[x] = kk;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn add_vx_kk(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let kk = (instruction & 0x00ff) as u8;
+    fn add_vx_kk(&mut self, x: usize, kk: u8) {
         let (res, _overflow) = self.v[x].overflowing_add(kk);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: ADD V[{:02x}]({:02x})+={:02x} -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                kk,
-                res
-            );
-        }
         self.v[x] = res;
-        self.pc += 2;
-    }
-
-    fn set_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SET V[{:02x}]({:02x})=V[{:02x}]({:02x})",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-            );
-        }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn set_vx_vy(&mut self, x: usize, y: usize) {
         self.v[x] = self.v[y];
-        self.pc += 2;
-    }
-
-    fn or_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: OR V[{:02x}]({:02x})|=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                self.v[x] | self.v[y],
-            );
-        }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn or_vx_vy(&mut self, x: usize, y: usize) {
         self.v[x] |= self.v[y];
-        self.pc += 2;
-    }
-
-    fn and_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: AND V[{:02x}]({:02x})&=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                self.v[x] & self.v[y],
-            );
+        if self.quirks.logic_resets_vf {
+            self.v[0xf] = 0;
         }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn and_vx_vy(&mut self, x: usize, y: usize) {
         self.v[x] &= self.v[y];
-        self.pc += 2;
-    }
-
-    fn xor_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: XOR V[{:02x}]({:02x})^=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                self.v[x] ^ self.v[y],
-            );
+        if self.quirks.logic_resets_vf {
+            self.v[0xf] = 0;
         }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn xor_vx_vy(&mut self, x: usize, y: usize) {
         self.v[x] ^= self.v[y];
-        self.pc += 2;
+        if self.quirks.logic_resets_vf {
+            self.v[0xf] = 0;
+        }
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn add_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
+    // The arithmetic and shift instructions write VF after Vx, so with VF as
+    // the destination the flag wins over the result.
+    fn add_vx_vy(&mut self, x: usize, y: usize) {
         let (res, carry) = self.v[x].overflowing_add(self.v[y]);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: ADD V[{:02x}]({:02x})+=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                res,
-            );
-        }
-        self.v[0xf] = (!carry) as u8;
         self.v[x] = res;
-        self.pc += 2;
-    }
-
-    fn sub_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        let (res, carry) = self.v[x].overflowing_sub(self.v[y]);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SUB V[{:02x}]({:02x})-=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                res,
-            );
-        }
-        
+        self.v[0xf] = carry as u8;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn sub_vx_vy(&mut self, x: usize, y: usize) {
+        let (res, borrow) = self.v[x].overflowing_sub(self.v[y]);
         self.v[x] = res;
-        self.v[0xf] = (!carry) as u8;
-        self.pc += 2;
-    }
-
-    fn shr_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        // let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SHR V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                self.v[x] >> 1,
-            );
-        }
-        self.v[0xf] = self.v[x] & 1;
-        self.v[x] >>= 1;
-        self.pc += 2;
-    }
-
-    fn subn_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        let (res, carry) = self.v[y].overflowing_sub(self.v[x]);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SUBN V[{:02x}]({:02x})-=V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                res,
-            );
-        }
-        self.v[0xf] = (!carry) as u8;
+        self.v[0xf] = (!borrow) as u8;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn shr_vx_vy(&mut self, x: usize, y: usize) {
+        let src = if self.quirks.shift_uses_vy { y } else { x };
+        let value = self.v[src];
+        self.v[x] = value >> 1;
+        self.v[0xf] = value & 1;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn subn_vx_vy(&mut self, x: usize, y: usize) {
+        let (res, borrow) = self.v[y].overflowing_sub(self.v[x]);
         self.v[x] = res;
-        self.pc += 2;
-    }
-
-    fn shl_vx_vy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        // let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SHL V[{:02x}]({:02x}) -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                self.v[x] << 1,
-            );
-        }
-        self.v[0xf] = self.v[x] & (1 << 7);
-        self.v[x] <<= 1;
-        self.pc += 2;
-    }
-
-    fn skip_ne_xy(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SKIP IF(V[{:02x}]({:02x})!=V[{:02x}]({:02x})) -> {}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                self.v[x] != self.v[y]
-            );
-        }
+        self.v[0xf] = (!borrow) as u8;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn shl_vx_vy(&mut self, x: usize, y: usize) {
+        let src = if self.quirks.shift_uses_vy { y } else { x };
+        let value = self.v[src];
+        self.v[x] = value << 1;
+        self.v[0xf] = value >> 7;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn skip_ne_xy(&mut self, x: usize, y: usize) {
         if self.v[x] != self.v[y] {
-            self.pc += 2;
+            self.skip_next();
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
-    fn set_i_nnn(&mut self, instruction: u16) {
-        let nnn = instruction & 0x0fff;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: SET I({:02x})={:04x}",
-                self.pc,
-                instruction,
-                self.i,
-                nnn,
-            );
-        }
+    fn set_i_nnn(&mut self, nnn: u16) {
         self.i = nnn;
-        self.pc += 2;
-    }
-
-    fn jump_v0(&mut self, instruction: u16) {
-        let nnn = instruction & 0x0fff;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: JP V[0]({:02x})+{:04x} -> {:04x}",
-                self.pc,
-                instruction,
-                self.v[0],
-                nnn,
-                u16::from(self.v[0]) + nnn,
-            );
-        }
-        self.pc = u16::from(self.v[0]) + nnn;
-    }
-
-    fn rnd(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let kk = (instruction & 0x00ff) as u8;
-        let random: u8 = rand::thread_rng().gen();
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: RND V[{:02x}]({:02x}) = rnd({:02x}) AND {:02x} -> {:02x}",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                random,
-                kk,
-                random & kk,
-            );
-        }
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    fn jump_v0(&mut self, nnn: u16) {
+        let x = if self.quirks.jump_uses_vx { usize::from(nnn >> 8) } else { 0 };
+        self.pc = u16::from(self.v[x]) + nnn;
+    }
+
+    fn rnd(&mut self, x: usize, kk: u8) {
+        let random = self.rng.next_byte();
         self.v[x] = random & kk;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
     
-    fn draw(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        let y = usize::from((instruction & 0x00f0) >> 4);
-        let n = (instruction & 0x000f) as u8;
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: DRW V[{:02x}]({:02x}) V[{:02x}]({:02x}) N({:02x})",
-                self.pc,
-                instruction,
-                x,
-                self.v[x],
-                y,
-                self.v[y],
-                n,
-            );
-        }
+    fn draw(&mut self, instruction: u16, x: usize, y: usize, n: u8) -> Result<(), Chip8Error> {
+        // Dxy0 draws a 16x16 sprite stored as two bytes per row. With two
+        // XO-CHIP planes selected the second plane's sprite follows the first.
+        let (rows, columns) = if n == 0 && self.has_super_chip() { (16, 16) } else { (usize::from(n), 8) };
+        let bytes_per_row = columns / 8;
+        let sprite_len = rows * bytes_per_row;
+        let planes: Vec<u8> = [1, 2].iter().cloned().filter(|bit| self.plane & bit != 0).collect();
+        self.check_memory(instruction, usize::from(self.i), sprite_len * planes.len())?;
+        let (width, height) = (self.width(), self.height());
+        let (origin_x, origin_y) = (self.v[x] as usize, self.v[y] as usize);
         self.v[0xf] = 0;
-        for i in 0..n {
-            for j in 0..8 {
-                let nx = (self.v[y] as u16 + i as u16) as usize & 0b11111;
-                let ny = (self.v[x] as u16 + j as u16) as usize & 0b111111;
-                let bit = self.memory[self.i as usize + i as usize] & (1 << (7-j));
-
-                if bit != 0 {
-                    self.display[nx][ny] ^= 1;
-                    if self.display[nx][ny] == 0 {
-                        self.v[0xf] = 1;
+        for (index, &plane) in planes.iter().enumerate() {
+            let sprite = self.i as usize + index * sprite_len;
+            for i in 0..rows {
+                for j in 0..columns {
+                    let (nx, ny) = if self.quirks.clip_sprites {
+                        // Only the starting position wraps, pixels past the edge are dropped.
+                        let nx = origin_y % height + i;
+                        let ny = origin_x % width + j;
+                        if nx >= height || ny >= width {
+                            continue;
+                        }
+                        (nx, ny)
+                    } else {
+                        ((origin_y + i) % height, (origin_x + j) % width)
+                    };
+                    let byte = self.memory[sprite + i * bytes_per_row + j / 8];
+                    let bit = byte & (1 << (7 - j % 8));
+
+                    if bit != 0 {
+                        self.display[nx][ny] ^= plane;
+                        if self.display[nx][ny] & plane == 0 {
+                            self.v[0xf] = 1;
+                        }
                     }
                 }
             }
         }
-        self.pc += 2;
+        if self.quirks.display_wait {
+            self.vblank_wait = true;
+        }
+        self.display_dirty = true;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
-    fn skip_key_pressed(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: SKIP KP V[{:02x}]({:02x}) -> {}", self.pc, instruction, x, self.v[x], self.keys[self.v[x] as usize]);
-        }
-        if self.keys[self.v[x] as usize] {
-            self.pc += 2;
+    fn skip_key_pressed(&mut self, instruction: u16, x: usize) -> Result<(), Chip8Error> {
+        let pressed = self.key_vx(instruction, x)?;
+        if pressed {
+            self.skip_next();
         }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
-    fn skip_key_not_pressed(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: SKIP NKP V[{:02x}]({:02x}) -> {}", self.pc, instruction, x, self.v[x], !self.keys[self.v[x] as usize]);
+    fn skip_key_not_pressed(&mut self, instruction: u16, x: usize) -> Result<(), Chip8Error> {
+        let pressed = self.key_vx(instruction, x)?;
+        if !pressed {
+            self.skip_next();
         }
-        if !self.keys[self.v[x] as usize] {
-            self.pc += 2;
-        }
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
-    fn set_vx_dt(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: LD V[{:02x}]({:02x}) = DT({:02x})", self.pc, instruction, x, self.v[x], self.delay);
-        }
+    fn set_vx_dt(&mut self, x: usize) {
         self.v[x] = self.delay;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx0A - LD Vx, K
     // Wait for a key press, store the value of the key in Vx.
     // All execution stops until a key is pressed, then the value of that key is stored in Vx.
-    fn wait_key(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: WAIT V[{:02x}]", self.pc, instruction, x);
-        }
+    fn wait_key(&mut self, x: usize) {
         self.waiting_register = x;
         self.is_waiting = true;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx15 - LD DT, Vx
     // Set delay timer = Vx.
     // DT is set equal to the value of Vx.
-    fn set_dt_vx(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: LD DT({:02x}) = V[{:02x}]({:02x})", self.pc, instruction, self.delay, x, self.v[x]);
-        }
+    fn set_dt_vx(&mut self, x: usize) {
         self.delay = self.v[x];
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx18 - LD ST, Vx
     // Set sound timer = Vx.
     // ST is set equal to the value of Vx.
-    fn set_sound_vx(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: LD ST({:02x}) = V[{:02x}]({:02x})", self.pc, instruction, self.sound, x, self.v[x]);
-        }
+    fn set_sound_vx(&mut self, x: usize) {
         self.sound = self.v[x];
-        self.pc += 2;
+        self.sounding |= self.sound > 0;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx1E - ADD I, Vx
     // Set I = I + Vx.
     // The values of I and Vx are added, and the results are stored in I.
-    fn add_i_vx(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: I({:04x}) += V[{:02x}]({:02x}) -> {:04x}",
-                self.pc,
-                instruction,
-                self.i,
-                x,
-                self.v[x],
-                self.i + (self.v[x] as u16)
-            );
-        }
-
-        self.i += self.v[x] as u16;
-        self.pc += 2;
+    fn add_i_vx(&mut self, x: usize) {
+        self.i = self.i.wrapping_add(self.v[x] as u16);
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx29 - LD F, Vx
     // Set I = location of sprite for digit Vx.
     // The value of I is set to the location for the hexadecimal sprite corresponding to the value of Vx.
-    fn load_sprite(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: LD I({:04x}) = SPRITE(V[{:02x}]({:02x}))",
-                self.pc,
-                instruction,
-                self.i,
-                x,
-                self.v[x]
-            );
-        }
+    fn load_sprite(&mut self, x: usize) {
         self.i = u16::from(self.v[x]) * 5;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Fx30 - LD HF, Vx
+    // Set I = location of the 8x10 sprite for digit Vx.
+    fn load_big_sprite(&mut self, x: usize) {
+        self.i = (Chip8::BIG_FONT_START + usize::from(self.v[x] & 0xf) * 10) as u16;
+        self.pc = self.pc.wrapping_add(2);
     }
 
     // Fx33 - LD B, Vx
     // Store BCD representation of Vx in memory locations I, I+1, and I+2.
     // The interpreter takes the decimal value of Vx, and places the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.
-    fn bcd(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!("{:04x} {:04x}: LD BCD V[{:02x}]({:02x})", self.pc, instruction, x, self.v[x]);
-        }
+    fn bcd(&mut self, instruction: u16, x: usize) -> Result<(), Chip8Error> {
+        self.check_memory(instruction, usize::from(self.i), 3)?;
         let mut num = self.v[x];
         self.memory[usize::from(self.i+2)] = num % 10;
         num /= 10;
         self.memory[usize::from(self.i+1)] = num % 10;
         num /= 10;
         self.memory[usize::from(self.i)] = num % 10;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
     // Fx55 - LD [I], Vx
     // Store registers V0 through Vx in memory starting at location I.
     // The interpreter copies the values of registers V0 through Vx into memory, starting at the address in I.
-    fn load_v0_vx_i(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: LD MEM[I({:04x})..(I+{:02x})({:04x})] = V[0..{:02x}]",
-                self.pc,
-                instruction,
-                self.i,
-                x,
-                self.i+(x as u16),
-                x,
-            );
-        }
+    fn load_v0_vx_i(&mut self, instruction: u16, x: usize) -> Result<(), Chip8Error> {
+        self.check_memory(instruction, usize::from(self.i), x + 1)?;
         for i in 0..x+1 {
             self.memory[usize::from(self.i)+i] = self.v[i];
         }
-        self.pc += 2;
+        self.i = self.i.wrapping_add(self.load_store_increment(x));
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
 
     // Fx65 - LD Vx, [I]
     // Read registers V0 through Vx from memory starting at location I.
     // The interpreter reads values from memory starting at location I into registers V0 through Vx.
-    fn load_i_v0_vx(&mut self, instruction: u16) {
-        let x = usize::from((instruction & 0x0f00) >> 8);
-        if Chip8::DEBUG_MODE {
-            println!(
-                "{:04x} {:04x}: LD V[0..{:02x}] = MEM[I({:04x})..(I+{:02x})({:04x})]",
-                self.pc,
-                instruction,
-                x,
-                self.i,
-                x,
-                self.i+(x as u16),
-            );
-        }
+    fn load_i_v0_vx(&mut self, instruction: u16, x: usize) -> Result<(), Chip8Error> {
+        self.check_memory(instruction, usize::from(self.i), x + 1)?;
         for i in 0..x+1 {
             self.v[i] = self.memory[usize::from(self.i)+i];
         }
-        self.pc += 2;
+        self.i = self.i.wrapping_add(self.load_store_increment(x));
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // How far Fx55/Fx65 advance I.
+    fn load_store_increment(&self, x: usize) -> u16 {
+        if self.quirks.load_store_increments_i_by_x {
+            x as u16
+        } else if self.quirks.load_store_increments_i {
+            x as u16 + 1
+        } else {
+            0
+        }
+    }
+
+    // Fx75 - LD R, Vx
+    // Store V0 through Vx in the RPL user flags.
+    fn save_rpl(&mut self, x: usize) {
+        self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Fx85 - LD Vx, R
+    // Read V0 through Vx from the RPL user flags.
+    fn load_rpl(&mut self, x: usize) {
+        self.v[..=x].copy_from_slice(&self.rpl[..=x]);
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // 5xy2 - SAVE Vx, Vy
+    // Store Vx through Vy, in either order, in memory starting at I. I is not changed.
+    fn save_range(&mut self, instruction: u16, x: usize, y: usize) -> Result<(), Chip8Error> {
+        let registers = Chip8::register_range(x, y);
+        self.check_memory(instruction, usize::from(self.i), registers.len())?;
+        for (offset, &register) in registers.iter().enumerate() {
+            self.memory[usize::from(self.i) + offset] = self.v[register];
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
     }
-}
\ No newline at end of file
+
+    // 5xy3 - LOAD Vx, Vy
+    // Read Vx through Vy, in either order, from memory starting at I. I is not changed.
+    fn load_range(&mut self, instruction: u16, x: usize, y: usize) -> Result<(), Chip8Error> {
+        let registers = Chip8::register_range(x, y);
+        self.check_memory(instruction, usize::from(self.i), registers.len())?;
+        for (offset, &register) in registers.iter().enumerate() {
+            self.v[register] = self.memory[usize::from(self.i) + offset];
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    fn register_range(x: usize, y: usize) -> Vec<usize> {
+        if x <= y {
+            (x..=y).collect()
+        } else {
+            (y..=x).rev().collect()
+        }
+    }
+
+    // F000 nnnn - LD I, LONG addr
+    // Set I = nnnn, a full 16-bit address stored in the next word.
+    fn set_i_long(&mut self, nnnn: u16) {
+        self.i = nnnn;
+        self.pc = self.pc.wrapping_add(4);
+    }
+
+    // Fn01 - PLANE n
+    // Select the bitplanes drawn to by CLS, DRW and the scroll instructions.
+    fn select_plane(&mut self, n: u8) {
+        self.plane = n & 0b11;
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // F002 - AUDIO
+    // Load the 16-byte audio pattern buffer from memory starting at I.
+    fn load_audio(&mut self, instruction: u16) -> Result<(), Chip8Error> {
+        let start = usize::from(self.i);
+        self.check_memory(instruction, start, 16)?;
+        self.audio_pattern.copy_from_slice(&self.memory[start..start + 16]);
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    // Fx3A - PITCH Vx
+    // Set the audio pattern playback rate to 4000*2^((Vx-64)/48) bits per second.
+    fn set_pitch(&mut self, x: usize) {
+        self.pitch = self.v[x];
+        self.pc = self.pc.wrapping_add(2);
+    }
+
+    // Skips the next instruction, which is four bytes long for F000 nnnn.
+    fn skip_next(&mut self) {
+        let next = usize::from(self.pc) + 2;
+        let long = self.xo_chip
+            && self.memory.get(next) == Some(&0xf0)
+            && self.memory.get(next + 1) == Some(&0x00);
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+    }
+}
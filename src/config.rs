@@ -0,0 +1,408 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use toml::value::{Table, Value};
+
+use crate::audio::{Tone, Waveform};
+use crate::chip8::Chip8;
+use crate::keymap::{Action, Keymap};
+use crate::movie::rom_hash;
+use crate::palette::Palette;
+
+// Frontend settings read from a TOML file, by default
+// `$XDG_CONFIG_HOME/chip8/config.toml`:
+//
+//     # Bind by physical key position instead of key name.
+//     scancodes = false
+//
+//     [keypad]
+//     5 = ["w", "up"]
+//     8 = ["s", "down"]
+//
+//     [hotkeys]
+//     pause = "space"
+//     step = "p"
+//     quit = ["escape", "q"]
+//     screenshot = "f12"
+//     gif = "f11"
+//     faster = "pageup"
+//     slower = "pagedown"
+//     fast_forward = "tab"    # held
+//     slow_motion = "m"
+//
+//     [display]
+//     palette = "amber"       # classic, green, amber or lcd
+//     foreground = "#ffcc00"  # replaces the preset's colours
+//     background = "#000000"
+//     scale = 10              # window pixels per lo-res pixel
+//     fade = 4                # frames a pixel takes to fade out, 0 for none
+//
+//     [audio]
+//     waveform = "sine"       # square or sine
+//     frequency = 440         # beep pitch in Hz, from 20 to 20000
+//     volume = 0.25           # from 0 to 1
+//
+//     [speed]
+//     ips = 700               # instructions per second
+//     fast_forward = 4        # speed-up while fast-forwarding, 0 for no limit
+//     slow_motion = 4         # slow-down in slow motion
+//
+//     # Per-ROM overrides, keyed by file name or `movie::rom_hash` in hex.
+//     [rom."Breakout [Carmelo Cortez, 1979].ch8".keypad]
+//     4 = ["left", "a"]
+//     6 = ["right", "d"]
+//
+//     [rom."Breakout [Carmelo Cortez, 1979].ch8".speed]
+//     ips = 1000
+//
+// Keypad keys are hex digits. Bindings are a key name or scancode, or a list
+// of them, and replace the default keys of that action; keys not mentioned
+// keep the layout of `Keymap::new`. With scancodes there is no default
+// layout, so only the keys bound here work, and quit has to be one of them.
+// ROMs can only override their keymap and instruction rate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub display: DisplaySettings,
+    pub audio: Tone,
+    pub speed: SpeedSettings,
+    keymap: Layer,
+    roms: Vec<(String, Layer)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplaySettings {
+    pub palette: Palette,
+    pub scale: u32,
+    pub fade_frames: u8,
+}
+
+impl DisplaySettings {
+    pub const MAX_SCALE: u32 = 32;
+}
+
+impl Default for DisplaySettings {
+    // The original look: white on black in a 640x320 window.
+    fn default() -> DisplaySettings {
+        DisplaySettings { palette: Palette::CLASSIC, scale: 10, fade_frames: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedSettings {
+    pub ips: u32,
+    // Multiplies the speed while fast-forwarding, 0 runs as fast as the
+    // host can.
+    pub fast_forward: u32,
+    // Divides the speed in slow motion.
+    pub slow_motion: u32,
+}
+
+impl SpeedSettings {
+    pub const MAX_IPS: u32 = 1_000_000;
+    pub const MAX_FACTOR: u32 = 100;
+}
+
+impl Default for SpeedSettings {
+    fn default() -> SpeedSettings {
+        SpeedSettings {
+            ips: Chip8::DEFAULT_INSTRUCTIONS_PER_SECOND,
+            fast_forward: 0,
+            slow_motion: 4,
+        }
+    }
+}
+
+// One layer of keymap settings and the instruction rate, applied over the
+// defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Layer {
+    scancodes: Option<bool>,
+    actions: Vec<(Action, Vec<String>)>,
+    ips: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    Toml(String),
+    Invalid { key: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Toml(message) => write!(f, "{}", message),
+            ConfigError::Invalid { key, message } => write!(f, "{}: {}", key, message),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+fn invalid(key: &str, message: &str) -> ConfigError {
+    ConfigError::Invalid { key: key.to_string(), message: message.to_string() }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let table: Table = toml::from_str(text).map_err(|err| ConfigError::Toml(err.to_string()))?;
+        let mut config = Config {
+            display: DisplaySettings::default(),
+            audio: Tone::default(),
+            speed: SpeedSettings::default(),
+            keymap: Layer::parse(&table, "")?,
+            roms: Vec::new(),
+        };
+        for (key, value) in &table {
+            match &key[..] {
+                "scancodes" | "keypad" | "hotkeys" => {},
+                "display" => {
+                    let display = value.as_table().ok_or_else(|| invalid(key, "expected a table"))?;
+                    config.display = DisplaySettings::parse(display)?;
+                },
+                "audio" => {
+                    let audio = value.as_table().ok_or_else(|| invalid(key, "expected a table"))?;
+                    config.audio = parse_tone(audio)?;
+                },
+                "speed" => {
+                    let speed = value.as_table().ok_or_else(|| invalid(key, "expected a table"))?;
+                    config.speed = SpeedSettings::parse(speed)?;
+                },
+                "rom" => {
+                    let roms = value.as_table().ok_or_else(|| invalid(key, "expected a table of ROMs"))?;
+                    for (rom, value) in roms {
+                        let prefix = format!("rom.{:?}.", rom);
+                        let table = value.as_table().ok_or_else(|| invalid(&prefix, "expected a table"))?;
+                        config.roms.push((rom.clone(), Layer::parse(table, &prefix)?));
+                    }
+                },
+                _ => return Err(invalid(key, "unknown setting")),
+            }
+        }
+        // Every ROM has to be able to quit, whatever layers apply to it.
+        let mut keymap = Keymap::new();
+        config.keymap.apply(&mut keymap);
+        check_quit(&keymap, "")?;
+        for (rom, layer) in &config.roms {
+            let mut rom_keymap = keymap.clone();
+            layer.apply(&mut rom_keymap);
+            check_quit(&rom_keymap, &format!("rom.{:?}.", rom))?;
+        }
+        Ok(config)
+    }
+
+    // `$XDG_CONFIG_HOME/chip8/config.toml`, falling back to `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("chip8").join("config.toml"))
+    }
+
+    // The keymap for a ROM, with the overrides matching its file name or
+    // hash applied over the global settings.
+    pub fn keymap(&self, rom_name: &str, rom: &[u8]) -> Keymap {
+        let mut keymap = Keymap::new();
+        for layer in self.layers(rom_name, rom) {
+            layer.apply(&mut keymap);
+        }
+        keymap
+    }
+
+    // The speed settings for a ROM, which can override the instruction rate.
+    pub fn speed(&self, rom_name: &str, rom: &[u8]) -> SpeedSettings {
+        let mut speed = self.speed;
+        for layer in self.layers(rom_name, rom) {
+            speed.ips = layer.ips.unwrap_or(speed.ips);
+        }
+        speed
+    }
+
+    // The global layer followed by the ROM overrides matching by name or
+    // hash.
+    fn layers<'a>(&'a self, rom_name: &'a str, rom: &[u8]) -> impl Iterator<Item = &'a Layer> {
+        let hash = format!("{:016x}", rom_hash(rom));
+        let roms = self.roms.iter()
+            .filter(move |(name, _)| *name == rom_name || name.eq_ignore_ascii_case(&hash))
+            .map(|(_, layer)| layer);
+        Some(&self.keymap).into_iter().chain(roms)
+    }
+}
+
+impl DisplaySettings {
+    fn parse(table: &Table) -> Result<DisplaySettings, ConfigError> {
+        let mut display = DisplaySettings::default();
+        // Explicit colours win over the preset, whatever the order.
+        if let Some(value) = table.get("palette") {
+            display.palette = value.as_str().and_then(Palette::from_name)
+                .ok_or_else(|| invalid("display.palette", "expected classic, green, amber or lcd"))?;
+        }
+        for (key, value) in table {
+            let name = format!("display.{}", key);
+            match &key[..] {
+                "palette" => {},
+                "foreground" | "background" => {
+                    let color = value.as_str().and_then(Palette::parse_color)
+                        .ok_or_else(|| invalid(&name, "expected a colour like \"#33ff66\""))?;
+                    if key == "foreground" {
+                        display.palette.set_foreground(color);
+                    } else {
+                        display.palette.set_background(color);
+                    }
+                },
+                "scale" => {
+                    display.scale = value.as_integer()
+                        .filter(|scale| (1..=i64::from(DisplaySettings::MAX_SCALE)).contains(scale))
+                        .ok_or_else(|| invalid(&name, "expected a whole number from 1 to 32"))? as u32;
+                },
+                "fade" => {
+                    display.fade_frames = value.as_integer()
+                        .filter(|frames| (0..=i64::from(u8::MAX)).contains(frames))
+                        .ok_or_else(|| invalid(&name, "expected a number of frames from 0 to 255"))? as u8;
+                },
+                _ => return Err(invalid(&name, "unknown setting")),
+            }
+        }
+        Ok(display)
+    }
+}
+
+fn parse_tone(table: &Table) -> Result<Tone, ConfigError> {
+    let mut tone = Tone::default();
+    for (key, value) in table {
+        let name = format!("audio.{}", key);
+        // TOML keeps whole numbers apart from floats, either will do.
+        let number = value.as_float().or_else(|| value.as_integer().map(|value| value as f64));
+        match &key[..] {
+            "waveform" => {
+                tone.waveform = value.as_str().and_then(Waveform::from_name)
+                    .ok_or_else(|| invalid(&name, "expected square or sine"))?;
+            },
+            "frequency" => {
+                tone.frequency = number.map(|frequency| frequency as f32)
+                    .filter(|frequency| (Tone::MIN_FREQUENCY..=Tone::MAX_FREQUENCY).contains(frequency))
+                    .ok_or_else(|| invalid(&name, "expected a frequency from 20 to 20000 Hz"))?;
+            },
+            "volume" => {
+                tone.volume = number.map(|volume| volume as f32)
+                    .filter(|volume| (0.0..=1.0).contains(volume))
+                    .ok_or_else(|| invalid(&name, "expected a volume from 0 to 1"))?;
+            },
+            _ => return Err(invalid(&name, "unknown setting")),
+        }
+    }
+    Ok(tone)
+}
+
+impl SpeedSettings {
+    fn parse(table: &Table) -> Result<SpeedSettings, ConfigError> {
+        let mut speed = SpeedSettings::default();
+        for (key, value) in table {
+            let name = format!("speed.{}", key);
+            match &key[..] {
+                "ips" => speed.ips = parse_ips(&name, value)?,
+                "fast_forward" | "slow_motion" => {
+                    let factor = value.as_integer()
+                        .filter(|factor| (0..=i64::from(SpeedSettings::MAX_FACTOR)).contains(factor))
+                        .ok_or_else(|| invalid(&name, "expected a whole number from 0 to 100"))? as u32;
+                    if key == "fast_forward" {
+                        speed.fast_forward = factor;
+                    } else if factor == 0 {
+                        return Err(invalid(&name, "expected a whole number from 1 to 100"));
+                    } else {
+                        speed.slow_motion = factor;
+                    }
+                },
+                _ => return Err(invalid(&name, "unknown setting")),
+            }
+        }
+        Ok(speed)
+    }
+}
+
+fn parse_ips(name: &str, value: &Value) -> Result<u32, ConfigError> {
+    value.as_integer()
+        .filter(|ips| (1..=i64::from(SpeedSettings::MAX_IPS)).contains(ips))
+        .map(|ips| ips as u32)
+        .ok_or_else(|| invalid(name, "expected instructions per second from 1 to 1000000"))
+}
+
+impl Layer {
+    fn parse(table: &Table, prefix: &str) -> Result<Layer, ConfigError> {
+        let mut layer = Layer::default();
+        for (key, value) in table {
+            let name = format!("{}{}", prefix, key);
+            match &key[..] {
+                "scancodes" => {
+                    layer.scancodes = Some(value.as_bool().ok_or_else(|| invalid(&name, "expected true or false"))?);
+                },
+                "keypad" => {
+                    let keypad = value.as_table().ok_or_else(|| invalid(&name, "expected a table"))?;
+                    for (digit, keys) in keypad {
+                        let name = format!("{}.{}", name, digit);
+                        let key = u8::from_str_radix(digit, 16).ok().filter(|&key| key < 0x10)
+                            .ok_or_else(|| invalid(&name, "expected a hex keypad key, 0 to f"))?;
+                        layer.actions.push((Action::Keypad(key), keys_of(&name, keys)?));
+                    }
+                },
+                "hotkeys" => {
+                    let hotkeys = value.as_table().ok_or_else(|| invalid(&name, "expected a table"))?;
+                    for (hotkey, keys) in hotkeys {
+                        let name = format!("{}.{}", name, hotkey);
+                        let action = Action::HOTKEYS.iter()
+                            .find(|&&(hotkey_name, _)| hotkey_name == hotkey)
+                            .map(|&(_, action)| action)
+                            .ok_or_else(|| invalid(&name, "unknown hotkey, expected pause, step, quit, screenshot, gif, \
+                                faster, slower, fast_forward or slow_motion"))?;
+                        layer.actions.push((action, keys_of(&name, keys)?));
+                    }
+                },
+                // Only valid at the top level, which checks the rest.
+                _ if prefix.is_empty() => {},
+                "speed" => {
+                    let speed = value.as_table().ok_or_else(|| invalid(&name, "expected a table"))?;
+                    for (key, value) in speed {
+                        let name = format!("{}.{}", name, key);
+                        match &key[..] {
+                            "ips" => layer.ips = Some(parse_ips(&name, value)?),
+                            _ => return Err(invalid(&name, "unknown setting, only ips can be set per ROM")),
+                        }
+                    }
+                },
+                _ => return Err(invalid(&name, "unknown setting")),
+            }
+        }
+        Ok(layer)
+    }
+
+    fn apply(&self, keymap: &mut Keymap) {
+        if let Some(scancodes) = self.scancodes {
+            keymap.set_scancodes(scancodes);
+        }
+        for (action, keys) in &self.actions {
+            keymap.bind(*action, keys);
+        }
+    }
+}
+
+fn check_quit(keymap: &Keymap, prefix: &str) -> Result<(), ConfigError> {
+    if keymap.uses_scancodes() && keymap.keys(Action::Quit).is_empty() {
+        return Err(invalid(&format!("{}scancodes", prefix), "binding by scancode needs a scancode for hotkeys.quit"));
+    }
+    Ok(())
+}
+
+// A key name or scancode, or a list of them.
+fn keys_of(name: &str, value: &Value) -> Result<Vec<String>, ConfigError> {
+    let key = |value: &Value| match value {
+        Value::String(key) => Ok(key.to_lowercase()),
+        Value::Integer(scancode) => Ok(scancode.to_string()),
+        _ => Err(invalid(name, "expected a key name or scancode")),
+    };
+    match value {
+        Value::Array(keys) => keys.iter().map(key).collect(),
+        _ => Ok(vec![key(value)?]),
+    }
+}
@@ -0,0 +1,545 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::Write;
+
+use crate::chip8::Chip8;
+use crate::error::Chip8Error;
+use crate::instruction::{decode_at, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+impl Register {
+    pub fn parse(name: &str) -> Option<Register> {
+        let name = name.to_ascii_lowercase();
+        match &name[..] {
+            "i" => Some(Register::I),
+            "pc" => Some(Register::Pc),
+            "sp" => Some(Register::Sp),
+            "dt" => Some(Register::Dt),
+            "st" => Some(Register::St),
+            _ => match name.strip_prefix('v').map(|x| u8::from_str_radix(x, 16)) {
+                Some(Ok(x)) if x < 0x10 => Some(Register::V(x)),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn read(&self, chip8: &Chip8) -> u16 {
+        match *self {
+            Register::V(x) => u16::from(chip8.v()[usize::from(x)]),
+            Register::I => chip8.i(),
+            Register::Pc => chip8.pc(),
+            Register::Sp => chip8.sp(),
+            Register::Dt => u16::from(chip8.delay()),
+            Register::St => u16::from(chip8.sound()),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<=", Comparison::Le),
+        (">=", Comparison::Ge),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+    ];
+}
+
+// Breakpoint condition such as `V3 == 0a`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn holds(&self, chip8: &Chip8) -> bool {
+        let value = self.register.read(chip8);
+        match self.comparison {
+            Comparison::Eq => value == self.value,
+            Comparison::Ne => value != self.value,
+            Comparison::Lt => value < self.value,
+            Comparison::Le => value <= self.value,
+            Comparison::Gt => value > self.value,
+            Comparison::Ge => value >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = Comparison::OPERATORS
+            .iter()
+            .find(|&&(_, comparison)| comparison == self.comparison)
+            .map_or("?", |&(operator, _)| operator);
+        write!(f, "{} {} {:02x}", self.register, operator, self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn covers(&self, access: Access) -> bool {
+        *self == Access::ReadWrite || *self == access
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Point {
+    Breakpoint { address: u16, condition: Option<Condition> },
+    Watchpoint { address: usize, len: usize, access: Access },
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Point::Breakpoint { address, condition: None } => write!(f, "break {:04x}", address),
+            Point::Breakpoint { address, condition: Some(condition) } => {
+                write!(f, "break {:04x} if {}", address, condition)
+            },
+            Point::Watchpoint { address, len, access } => {
+                let kind = match access {
+                    Access::Read => "rwatch",
+                    Access::Write => "watch",
+                    Access::ReadWrite => "awatch",
+                };
+                write!(f, "{} {:04x} {}", kind, address, len)
+            },
+        }
+    }
+}
+
+// Why a running machine was stopped by the debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint { id: usize },
+    Watchpoint { id: usize, address: usize, access: Access },
+    // Step-over or step-out finished.
+    Step,
+    Halted,
+    Fault(Chip8Error),
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stop::Breakpoint { id } => write!(f, "breakpoint {}", id),
+            Stop::Watchpoint { id, address, access } => {
+                let access = if access == Access::Write { "write to" } else { "read from" };
+                write!(f, "watchpoint {}: {} {:04x}", id, access, address)
+            },
+            Stop::Step => write!(f, "step finished"),
+            Stop::Halted => write!(f, "machine halted"),
+            Stop::Fault(err) => write!(f, "fault: {}", err),
+        }
+    }
+}
+
+// Runs until the machine returns to `pc` at stack depth `sp`.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    StepOver { pc: u16, sp: u16 },
+    StepOut { sp: u16 },
+}
+
+// Breakpoints, watchpoints and run control for a `Chip8`. While running,
+// the frontend calls `run_frame` instead of `Chip8::step_frame`; every
+// instruction is checked against the breakpoints and the memory it is about
+// to access before it executes.
+pub struct Debugger {
+    points: BTreeMap<usize, Point>,
+    next_id: usize,
+    running: bool,
+    target: Option<Target>,
+    // Set on resume so the instruction we stopped at doesn't stop us again.
+    skip_check: bool,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    // Creates a debugger with the machine stopped.
+    pub fn new() -> Debugger {
+        Debugger { points: BTreeMap::new(), next_id: 1, running: false, target: None, skip_check: false }
+    }
+
+    pub fn add(&mut self, point: Point) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.points.insert(id, point);
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Point> {
+        self.points.remove(&id)
+    }
+
+    pub fn points(&self) -> &BTreeMap<usize, Point> {
+        &self.points
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn resume(&mut self) {
+        self.running = true;
+        self.skip_check = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.running = false;
+        self.target = None;
+    }
+
+    // Runs the machine to the end of the current frame unless a breakpoint,
+    // watchpoint, step target, halt or fault stops it first. Does nothing
+    // while stopped.
+    pub fn run_frame(&mut self, chip8: &mut Chip8) -> Option<Stop> {
+        if !self.running {
+            return None;
+        }
+        let frame = chip8.frames();
+        while chip8.frames() == frame {
+            if chip8.is_halted() {
+                self.pause();
+                return Some(Stop::Halted);
+            }
+            if Debugger::will_execute(chip8) {
+                if self.skip_check {
+                    self.skip_check = false;
+                } else if let Some(stop) = self.check(chip8) {
+                    self.pause();
+                    return Some(stop);
+                }
+            }
+            if let Err(err) = chip8.step() {
+                self.pause();
+                return Some(Stop::Fault(err));
+            }
+            let reached = match self.target {
+                Some(Target::StepOver { pc, sp }) => chip8.pc() == pc && chip8.sp() == sp,
+                Some(Target::StepOut { sp }) => chip8.sp() < sp,
+                None => false,
+            };
+            if reached {
+                self.pause();
+                return Some(Stop::Step);
+            }
+        }
+        None
+    }
+
+    // Executes exactly one instruction, skipping over cycles spent waiting
+    // for the display. Breakpoints are ignored.
+    pub fn step(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        while !chip8.is_halted() && !chip8.is_waiting() {
+            let executes = Debugger::will_execute(chip8);
+            chip8.step()?;
+            if executes {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Steps over CALL by running until it returns, other instructions are
+    // stepped normally. Returns true if the machine was resumed.
+    pub fn step_over(&mut self, chip8: &mut Chip8) -> Result<bool, Chip8Error> {
+        match decode_at(chip8.memory(), usize::from(chip8.pc())) {
+            Some(Instruction::Call(_)) => {
+                self.target = Some(Target::StepOver { pc: chip8.pc().wrapping_add(2), sp: chip8.sp() });
+                self.resume();
+                Ok(true)
+            },
+            _ => self.step(chip8).map(|_| false),
+        }
+    }
+
+    // Runs until the current subroutine returns.
+    pub fn step_out(&mut self, chip8: &Chip8) -> bool {
+        if chip8.sp() == 0 {
+            return false;
+        }
+        self.target = Some(Target::StepOut { sp: chip8.sp() });
+        self.resume();
+        true
+    }
+
+    fn will_execute(chip8: &Chip8) -> bool {
+        !chip8.is_halted() && !chip8.is_waiting() && !chip8.is_waiting_for_vblank()
+    }
+
+    // Checks the instruction at PC against every breakpoint and watchpoint.
+    pub fn check(&self, chip8: &Chip8) -> Option<Stop> {
+        let access = memory_access(chip8);
+        for (&id, point) in &self.points {
+            match *point {
+                Point::Breakpoint { address, condition } => {
+                    if address == chip8.pc() && condition.is_none_or(|condition| condition.holds(chip8)) {
+                        return Some(Stop::Breakpoint { id });
+                    }
+                },
+                Point::Watchpoint { address, len, access: watched } => {
+                    if let Some((start, accessed_len, access)) = access {
+                        let overlap_start = start.max(address);
+                        if watched.covers(access) && overlap_start < start.saturating_add(accessed_len).min(address.saturating_add(len)) {
+                            return Some(Stop::Watchpoint { id, address: overlap_start, access });
+                        }
+                    }
+                },
+            }
+        }
+        None
+    }
+
+    // Runs a console command and returns its output. Addresses and values
+    // are hex, counts are decimal.
+    pub fn execute(&mut self, chip8: &mut Chip8, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.split_first() {
+            None => Ok(String::new()),
+            Some((&command, args)) => self.command(chip8, command, args),
+        };
+        match result {
+            Ok(output) => output,
+            Err(message) => format!("error: {}\n", message),
+        }
+    }
+
+    fn command(&mut self, chip8: &mut Chip8, command: &str, args: &[&str]) -> Result<String, String> {
+        match command {
+            "b" | "break" => {
+                let address = parse_hex(args.first())?;
+                let condition = match args.get(1..) {
+                    Some([]) | None => None,
+                    Some(["if", register, operator, value]) => Some(parse_condition(register, operator, value)?),
+                    Some(_) => return Err(String::from("usage: break <addr> [if <reg> <op> <value>]")),
+                };
+                let point = Point::Breakpoint { address, condition };
+                Ok(format!("{}: {}\n", self.add(point), point))
+            },
+            "watch" | "rwatch" | "awatch" => {
+                let access = match command {
+                    "watch" => Access::Write,
+                    "rwatch" => Access::Read,
+                    _ => Access::ReadWrite,
+                };
+                let address = usize::from(parse_hex(args.first())?);
+                let len = match args.get(1) {
+                    Some(len) => len.parse().map_err(|_| format!("bad length {:?}", len))?,
+                    None => 1,
+                };
+                let point = Point::Watchpoint { address, len, access };
+                Ok(format!("{}: {}\n", self.add(point), point))
+            },
+            "d" | "delete" => match args.first() {
+                Some(id) => {
+                    let id = id.parse().map_err(|_| format!("bad id {:?}", id))?;
+                    match self.remove(id) {
+                        Some(point) => Ok(format!("deleted {}: {}\n", id, point)),
+                        None => Err(format!("no breakpoint or watchpoint {}", id)),
+                    }
+                },
+                None => {
+                    self.points.clear();
+                    Ok(String::from("deleted all breakpoints and watchpoints\n"))
+                },
+            },
+            "info" => Ok(self.points.iter().map(|(id, point)| format!("{}: {}\n", id, point)).collect()),
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(count) => count.parse().map_err(|_| format!("bad count {:?}", count))?,
+                    None => 1,
+                };
+                self.pause();
+                for _ in 0..count {
+                    self.step(chip8).map_err(|err| err.to_string())?;
+                }
+                Ok(location(chip8))
+            },
+            "n" | "next" => {
+                self.pause();
+                match self.step_over(chip8) {
+                    Ok(true) => Ok(String::new()),
+                    Ok(false) => Ok(location(chip8)),
+                    Err(err) => Err(err.to_string()),
+                }
+            },
+            "finish" => {
+                self.pause();
+                if self.step_out(chip8) {
+                    Ok(String::new())
+                } else {
+                    Err(String::from("not in a subroutine"))
+                }
+            },
+            "c" | "continue" => {
+                self.resume();
+                Ok(String::new())
+            },
+            "pause" => {
+                self.pause();
+                Ok(location(chip8))
+            },
+            "r" | "regs" => Ok(registers(chip8)),
+            "stack" => {
+                let mut output = String::new();
+                for (depth, addr) in chip8.stack()[..usize::from(chip8.sp())].iter().enumerate().rev() {
+                    writeln!(output, "#{} {:04x}", depth, addr).unwrap();
+                }
+                Ok(output)
+            },
+            "x" => {
+                let address = usize::from(parse_hex(args.first())?);
+                let len = match args.get(1) {
+                    Some(len) => len.parse().map_err(|_| format!("bad length {:?}", len))?,
+                    None => 16,
+                };
+                let memory = chip8.memory();
+                let end = address.saturating_add(len).min(memory.len());
+                let mut output = String::new();
+                for row in (address.min(end)..end).step_by(16) {
+                    let bytes: Vec<String> = memory[row..(row + 16).min(end)]
+                        .iter()
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect();
+                    writeln!(output, "{:04x}: {}", row, bytes.join(" ")).unwrap();
+                }
+                Ok(output)
+            },
+            "h" | "help" => Ok(String::from(HELP)),
+            _ => Err(format!("unknown command {:?}, try help", command)),
+        }
+    }
+}
+
+const HELP: &str = "\
+break <addr> [if <reg> <op> <value>]  stop at addr, optionally only if the condition holds
+watch|rwatch|awatch <addr> [len]      stop on writes, reads or both
+delete [id]                           remove one or all breakpoints and watchpoints
+info                                  list breakpoints and watchpoints
+step [n]                              execute n instructions
+next                                  step over CALL
+finish                                run until the current subroutine returns
+continue                              resume execution
+pause                                 stop execution
+regs                                  show registers and timers
+stack                                 show the call stack
+x <addr> [len]                        dump memory
+registers are V0-VF, I, PC, SP, DT and ST, operators ==, !=, <, <=, >, >=
+";
+
+// Current PC and the instruction there, e.g. `0204: LD I, 0x2ea`.
+pub fn location(chip8: &Chip8) -> String {
+    match decode_at(chip8.memory(), usize::from(chip8.pc())) {
+        Some(instruction) => format!("{:04x}: {}\n", chip8.pc(), instruction),
+        None => format!("{:04x}: ???\n", chip8.pc()),
+    }
+}
+
+pub fn registers(chip8: &Chip8) -> String {
+    let mut output = String::new();
+    for (x, value) in chip8.v().iter().enumerate() {
+        write!(output, "V{:X}={:02x}{}", x, value, if x % 8 == 7 { "\n" } else { " " }).unwrap();
+    }
+    writeln!(
+        output,
+        "I={:04x} PC={:04x} SP={:02x} DT={:02x} ST={:02x}",
+        chip8.i(),
+        chip8.pc(),
+        chip8.sp(),
+        chip8.delay(),
+        chip8.sound(),
+    )
+    .unwrap();
+    output
+}
+
+// Memory range the instruction at PC is about to read or write, as
+// (start, length, access). Instruction fetches are not counted.
+pub fn memory_access(chip8: &Chip8) -> Option<(usize, usize, Access)> {
+    let i = usize::from(chip8.i());
+    let registers = |x: u8, y: u8| usize::from(x.max(y) - x.min(y)) + 1;
+    match decode_at(chip8.memory(), usize::from(chip8.pc()))? {
+        Instruction::Drw { n, .. } => {
+            let sprite_len = if n == 0 { 32 } else { usize::from(n) };
+            let planes = (chip8.plane() & 0b11).count_ones() as usize;
+            Some((i, sprite_len * planes, Access::Read))
+        },
+        Instruction::LdVxI { x } => Some((i, usize::from(x) + 1, Access::Read)),
+        Instruction::Load { x, y } => Some((i, registers(x, y), Access::Read)),
+        Instruction::Audio => Some((i, 16, Access::Read)),
+        Instruction::LdIVx { x } => Some((i, usize::from(x) + 1, Access::Write)),
+        Instruction::Save { x, y } => Some((i, registers(x, y), Access::Write)),
+        Instruction::LdBVx { .. } => Some((i, 3, Access::Write)),
+        _ => None,
+    }
+}
+
+// A 16-bit value, which covers every address and register.
+fn parse_hex(value: Option<&&str>) -> Result<u16, String> {
+    match value {
+        Some(value) => {
+            let digits = value.trim_start_matches("0x");
+            let parsed = u32::from_str_radix(digits, 16).map_err(|_| format!("bad hex value {:?}", value))?;
+            u16::try_from(parsed).map_err(|_| format!("hex value {:?} is above ffff", value))
+        },
+        None => Err(String::from("missing address")),
+    }
+}
+
+fn parse_condition(register: &str, operator: &str, value: &str) -> Result<Condition, String> {
+    let register = Register::parse(register).ok_or_else(|| format!("unknown register {:?}", register))?;
+    let comparison = Comparison::OPERATORS
+        .iter()
+        .find(|&&(name, _)| name == operator)
+        .map(|&(_, comparison)| comparison)
+        .ok_or_else(|| format!("unknown operator {:?}", operator))?;
+    let value = parse_hex(Some(&value))?;
+    Ok(Condition { register, comparison, value })
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::instruction::{decode_at, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelKind {
+    Start,
+    Jump,
+    Subroutine,
+    Data,
+}
+
+// Result of a recursive-descent disassembly of a ROM loaded at 0x200.
+// Bytes reachable from the entry point through jumps, calls and skips are
+// code, everything else is treated as data.
+pub struct Disassembly<'a> {
+    rom: &'a [u8],
+    // Bytes covered by an instruction, and the first byte of each instruction.
+    code: Vec<bool>,
+    instructions: Vec<bool>,
+    labels: BTreeMap<u16, LabelKind>,
+}
+
+pub fn disassemble(rom: &[u8]) -> Disassembly<'_> {
+    // Whatever doesn't fit below the top of the address space can't be loaded.
+    let rom = &rom[..rom.len().min(Chip8::XO_CHIP_MEMORY_SIZE - Chip8::PROGRAM_START)];
+    let mut code = vec![false; rom.len()];
+    let mut instructions = vec![false; rom.len()];
+    let mut targets: BTreeMap<u16, LabelKind> = BTreeMap::new();
+    let mut pending = vec![Chip8::PROGRAM_START as u16];
+    targets.insert(Chip8::PROGRAM_START as u16, LabelKind::Start);
+
+    while let Some(addr) = pending.pop() {
+        let offset = match usize::from(addr).checked_sub(Chip8::PROGRAM_START) {
+            Some(offset) if offset + 1 < rom.len() => offset,
+            _ => continue,
+        };
+        // 0000 halts the emulator, anything it can't decode is data.
+        let instruction = match decode_at(rom, offset) {
+            Some(_) if rom[offset] == 0 && rom[offset + 1] == 0 => continue,
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        let size = instruction.size();
+        if code[offset..offset + size].iter().any(|&claimed| claimed) {
+            continue;
+        }
+        for claimed in &mut code[offset..offset + size] {
+            *claimed = true;
+        }
+        instructions[offset] = true;
+
+        // Execution stops at the top of the address space.
+        let next = addr.checked_add(size as u16);
+        match instruction {
+            Instruction::Jp(nnn) => {
+                targets.entry(nnn).or_insert(LabelKind::Jump);
+                pending.push(nnn);
+            },
+            Instruction::Call(nnn) => {
+                targets.insert(nnn, LabelKind::Subroutine);
+                pending.push(nnn);
+                pending.extend(next);
+            },
+            // The target of JP V0 depends on V0, only the table base is labelled.
+            Instruction::JpV0(nnn) => {
+                targets.entry(nnn).or_insert(LabelKind::Jump);
+            },
+            Instruction::Ret | Instruction::Exit => {},
+            Instruction::SeVxByte { .. }
+            | Instruction::SneVxByte { .. }
+            | Instruction::SeVxVy { .. }
+            | Instruction::SneVxVy { .. }
+            | Instruction::Skp { .. }
+            | Instruction::Sknp { .. } => {
+                // Skipping over F000 nnnn lands two bytes further.
+                let skipped = match decode_at(rom, offset + size) {
+                    Some(Instruction::LdILong(_)) => 4,
+                    _ => 2,
+                };
+                pending.extend(next);
+                pending.extend(next.and_then(|next| next.checked_add(skipped)));
+            },
+            Instruction::LdI(nnn) | Instruction::LdILong(nnn) => {
+                targets.entry(nnn).or_insert(LabelKind::Data);
+                pending.extend(next);
+            },
+            _ => pending.extend(next),
+        }
+    }
+
+    let mut disassembly = Disassembly { rom, code, instructions, labels: BTreeMap::new() };
+    // Only addresses that start a listing line can carry a label.
+    disassembly.labels = targets
+        .into_iter()
+        .filter(|&(addr, _)| disassembly.starts_line(addr))
+        .collect();
+    disassembly
+}
+
+impl<'a> Disassembly<'a> {
+    pub fn is_code(&self, addr: u16) -> bool {
+        match self.offset(addr) {
+            Some(offset) => self.code[offset],
+            None => false,
+        }
+    }
+
+    pub fn label(&self, addr: u16) -> Option<String> {
+        self.labels.get(&addr).map(|kind| match kind {
+            LabelKind::Start => String::from("start"),
+            LabelKind::Jump => format!("label_{:03x}", addr),
+            LabelKind::Subroutine => format!("sub_{:03x}", addr),
+            LabelKind::Data => format!("data_{:03x}", addr),
+        })
+    }
+
+    pub fn labels(&self) -> &BTreeMap<u16, LabelKind> {
+        &self.labels
+    }
+
+    fn offset(&self, addr: u16) -> Option<usize> {
+        match usize::from(addr).checked_sub(Chip8::PROGRAM_START) {
+            Some(offset) if offset < self.rom.len() => Some(offset),
+            _ => None,
+        }
+    }
+
+    // Code is listed one instruction per line, data one byte per line.
+    fn starts_line(&self, addr: u16) -> bool {
+        match self.offset(addr) {
+            Some(offset) => self.instructions[offset] || !self.code[offset],
+            None => false,
+        }
+    }
+
+    fn operand(&self, addr: u16) -> String {
+        match self.label(addr) {
+            Some(label) => label,
+            None => format!("{:#05x}", addr),
+        }
+    }
+
+    fn mnemonic(&self, instruction: Instruction) -> String {
+        match instruction {
+            Instruction::Sys(nnn) => format!("SYS {}", self.operand(nnn)),
+            Instruction::Jp(nnn) => format!("JP {}", self.operand(nnn)),
+            Instruction::Call(nnn) => format!("CALL {}", self.operand(nnn)),
+            Instruction::LdI(nnn) => format!("LD I, {}", self.operand(nnn)),
+            Instruction::JpV0(nnn) => format!("JP V0, {}", self.operand(nnn)),
+            Instruction::LdILong(nnnn) => match self.label(nnnn) {
+                Some(label) => format!("LD I, LONG {}", label),
+                None => instruction.to_string(),
+            },
+            _ => instruction.to_string(),
+        }
+    }
+}
+
+// Prints an assembler-compatible listing with the address and raw bytes of
+// every line in a trailing comment.
+impl<'a> fmt::Display for Disassembly<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut offset = 0;
+        while offset < self.rom.len() {
+            let addr = (Chip8::PROGRAM_START + offset) as u16;
+            if let Some(label) = self.label(addr) {
+                writeln!(f, "{}:", label)?;
+            }
+            if self.instructions[offset] {
+                let instruction = decode_at(self.rom, offset).expect("code bytes always decode");
+                let bytes: String = self.rom[offset..offset + instruction.size()]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                writeln!(f, "    {:<24}; {:03x}: {}", self.mnemonic(instruction), addr, bytes)?;
+                offset += instruction.size();
+            } else {
+                let byte = self.rom[offset];
+                let sprite: String = (0..8)
+                    .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                    .collect();
+                let data = format!("db {:#010b}", byte);
+                writeln!(f, "    {:<24}; {:03x}: {:02x}    {}", data, addr, byte, sprite)?;
+                offset += 1;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::fmt;
+
+// Machine faults raised by `Chip8::clock`. Each variant carries the address
+// of the faulting instruction and its opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    StackOverflow { pc: u16, opcode: u16 },
+    StackUnderflow { pc: u16, opcode: u16 },
+    MemoryOutOfBounds { pc: u16, opcode: u16, address: usize },
+    InvalidKey { pc: u16, opcode: u16, key: u8 },
+}
+
+impl Chip8Error {
+    pub fn pc(&self) -> u16 {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, .. }
+            | Chip8Error::StackOverflow { pc, .. }
+            | Chip8Error::StackUnderflow { pc, .. }
+            | Chip8Error::MemoryOutOfBounds { pc, .. }
+            | Chip8Error::InvalidKey { pc, .. } => pc,
+        }
+    }
+
+    pub fn opcode(&self) -> u16 {
+        match *self {
+            Chip8Error::UnknownOpcode { opcode, .. }
+            | Chip8Error::StackOverflow { opcode, .. }
+            | Chip8Error::StackUnderflow { opcode, .. }
+            | Chip8Error::MemoryOutOfBounds { opcode, .. }
+            | Chip8Error::InvalidKey { opcode, .. } => opcode,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04x} {:04x}: ", self.pc(), self.opcode())?;
+        match *self {
+            Chip8Error::UnknownOpcode { .. } => write!(f, "unknown opcode"),
+            Chip8Error::StackOverflow { .. } => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow { .. } => write!(f, "return with empty stack"),
+            Chip8Error::MemoryOutOfBounds { address, .. } => {
+                write!(f, "memory access out of bounds at {:04x}", address)
+            },
+            Chip8Error::InvalidKey { key, .. } => write!(f, "invalid key {:02x}", key),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
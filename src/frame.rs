@@ -0,0 +1,107 @@
+use std::io;
+use std::io::prelude::*;
+
+use crate::chip8::Chip8;
+use crate::movie::rom_hash;
+use crate::palette::Palette;
+
+// RGB colours for the four combinations of the two XO-CHIP planes.
+pub const PALETTE: [[u8; 3]; 4] = [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]];
+
+// Characters for the same four combinations in `ascii`.
+const ASCII: [char; 4] = ['.', '#', '+', '*'];
+
+// Visible pixels of the framebuffer, row by row.
+pub fn pixels(chip8: &Chip8) -> impl Iterator<Item = &[u8]> {
+    let width = chip8.width();
+    chip8.display().iter().take(chip8.height()).map(move |row| &row[..width])
+}
+
+// One line per row, `#` for lit pixels and `.` for dark ones.
+pub fn ascii(chip8: &Chip8) -> String {
+    let mut text = String::with_capacity((chip8.width() + 1) * chip8.height());
+    for row in pixels(chip8) {
+        text.extend(row.iter().map(|&pixel| ASCII[usize::from(pixel & 0b11)]));
+        text.push('\n');
+    }
+    text
+}
+
+// Two pixel rows per character using the upper and lower half blocks, so
+// 64x32 fits in 64x16 terminal cells. Any lit plane counts as lit.
+pub fn half_blocks(chip8: &Chip8) -> String {
+    let (width, height) = (chip8.width(), chip8.height());
+    let display = chip8.display();
+    let mut text = String::with_capacity((width * 3 + 1) * height / 2);
+    for y in (0..height).step_by(2) {
+        for (&upper, &lower) in display[y][..width].iter().zip(&display[y + 1][..width]) {
+            text.push(match (upper != 0, lower != 0) {
+                (false, false) => ' ',
+                (true, false) => '\u{2580}',
+                (false, true) => '\u{2584}',
+                (true, true) => '\u{2588}',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// 2x4 pixels per braille character, so the 128x64 hi-res display fits in
+// 64x16 terminal cells.
+pub fn braille(chip8: &Chip8) -> String {
+    // Dot bits of a braille cell, indexed by row then column.
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let (width, height) = (chip8.width(), chip8.height());
+    let display = chip8.display();
+    let mut text = String::with_capacity((width / 2 * 3 + 1) * height / 4);
+    for y in (0..height).step_by(4) {
+        for x in (0..width).step_by(2) {
+            let mut cell = 0;
+            for (dy, dots) in DOTS.iter().enumerate() {
+                for (dx, dot) in dots.iter().enumerate() {
+                    if display[y + dy][x + dx] != 0 {
+                        cell |= dot;
+                    }
+                }
+            }
+            text.push(char::from_u32(0x2800 + cell).unwrap());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Hash of the visible framebuffer and its resolution, for comparing
+// against known-good results.
+pub fn hash(chip8: &Chip8) -> u64 {
+    let mut bytes = vec![chip8.width() as u8, chip8.height() as u8];
+    for row in pixels(chip8) {
+        bytes.extend_from_slice(row);
+    }
+    rom_hash(&bytes)
+}
+
+// Writes the framebuffer as an RGB PNG in the colours of `palette`, with
+// every pixel scaled to a `scale` x `scale` square.
+pub fn write_png<W: Write>(chip8: &Chip8, palette: &Palette, output: W, scale: usize) -> io::Result<()> {
+    let scale = scale.max(1);
+    let (width, height) = (chip8.width() * scale, chip8.height() * scale);
+    let mut data = Vec::with_capacity(width * height * 3);
+    for row in pixels(chip8) {
+        let mut line = Vec::with_capacity(width * 3);
+        for &pixel in row {
+            for _ in 0..scale {
+                line.extend_from_slice(&palette.colors[usize::from(pixel & 0b11)]);
+            }
+        }
+        for _ in 0..scale {
+            data.extend_from_slice(&line);
+        }
+    }
+    let mut encoder = png::Encoder::new(output, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&data).map_err(io::Error::other)
+}
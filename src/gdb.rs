@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::chip8::Chip8;
+use crate::debugger::{Access, Debugger, Point, Stop};
+use crate::error::Chip8Error;
+
+// Register file as seen by gdb, in `g` packet order. Multi-byte registers
+// are sent big-endian like everything else on the CHIP-8.
+//
+//     0-15  V0-VF  8 bits
+//     16    I      16 bits
+//     17    PC     16 bits
+//     18    SP     8 bits
+//     19    DT     8 bits
+//     20    ST     8 bits
+const REGISTER_SIZES: [usize; 21] = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 1, 1, 1];
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" regnum="0"/>
+    <reg name="v1" bitsize="8"/>
+    <reg name="v2" bitsize="8"/>
+    <reg name="v3" bitsize="8"/>
+    <reg name="v4" bitsize="8"/>
+    <reg name="v5" bitsize="8"/>
+    <reg name="v6" bitsize="8"/>
+    <reg name="v7" bitsize="8"/>
+    <reg name="v8" bitsize="8"/>
+    <reg name="v9" bitsize="8"/>
+    <reg name="va" bitsize="8"/>
+    <reg name="vb" bitsize="8"/>
+    <reg name="vc" bitsize="8"/>
+    <reg name="vd" bitsize="8"/>
+    <reg name="ve" bitsize="8"/>
+    <reg name="vf" bitsize="8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8"/>
+    <reg name="dt" bitsize="8"/>
+    <reg name="st" bitsize="8"/>
+  </feature>
+</target>
+"#;
+
+// GDB remote serial protocol server for a `Chip8`. It never blocks: the
+// frontend calls `poll` to service the connection and `run_frame` in place
+// of `Chip8::step_frame`. The machine is stopped while no client is
+// attached and until the client continues it.
+pub struct GdbStub {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+    input: Vec<u8>,
+    debugger: Debugger,
+    // Debugger ids of the breakpoints and watchpoints set by the client,
+    // keyed by Z packet type and address.
+    points: HashMap<(u8, usize), usize>,
+}
+
+impl GdbStub {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<GdbStub> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbStub {
+            listener,
+            client: None,
+            input: Vec::new(),
+            debugger: Debugger::new(),
+            points: HashMap::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.debugger.is_running()
+    }
+
+    // Accepts a client and handles every packet received since the last
+    // call. A dropped connection leaves the machine stopped for the next one.
+    pub fn poll(&mut self, chip8: &mut Chip8) -> io::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.client = Some(stream);
+                    self.input.clear();
+                    self.debugger.pause();
+                },
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut buffer = [0; 4096];
+        loop {
+            let read = match self.client.as_mut() {
+                Some(client) => client.read(&mut buffer),
+                None => return Ok(()),
+            };
+            match read {
+                Ok(0) => {
+                    self.disconnect();
+                    return Ok(());
+                },
+                Ok(n) => self.input.extend_from_slice(&buffer[..n]),
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    self.disconnect();
+                    return Err(err);
+                },
+            }
+        }
+        while let Some(packet) = self.next_packet()? {
+            self.handle(chip8, &packet)?;
+        }
+        Ok(())
+    }
+
+    // Runs a frame while the client has the machine running and reports
+    // the reason when it stops.
+    pub fn run_frame(&mut self, chip8: &mut Chip8) -> io::Result<()> {
+        match self.debugger.run_frame(chip8) {
+            Some(stop) => {
+                let reply = self.stop_reply(stop);
+                self.send(&reply)
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.client = None;
+        self.input.clear();
+        self.debugger.pause();
+        for (_, id) in self.points.drain() {
+            self.debugger.remove(id);
+        }
+    }
+
+    // Pulls the next `$packet#xx` out of the input, acknowledging it.
+    // A bare 0x03 is an interrupt request and comes back as "\x03".
+    fn next_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.input.first() {
+                None => return Ok(None),
+                Some(0x03) => {
+                    self.input.remove(0);
+                    return Ok(Some(String::from("\x03")));
+                },
+                Some(b'$') => break,
+                // Acks for our own packets and line noise.
+                Some(_) => {
+                    self.input.remove(0);
+                },
+            }
+        }
+        let end = match self.input.iter().position(|&byte| byte == b'#') {
+            Some(end) if end + 2 < self.input.len() => end,
+            _ => return Ok(None),
+        };
+        let packet: Vec<u8> = self.input.drain(..end + 3).collect();
+        let data = &packet[1..end];
+        let checksum = std::str::from_utf8(&packet[end + 1..]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        let valid = checksum == Some(data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)));
+        self.write_raw(if valid { b"+" } else { b"-" })?;
+        if valid {
+            Ok(Some(String::from_utf8_lossy(data).into_owned()))
+        } else {
+            self.next_packet()
+        }
+    }
+
+    fn handle(&mut self, chip8: &mut Chip8, packet: &str) -> io::Result<()> {
+        if packet == "\x03" {
+            if self.debugger.is_running() {
+                self.debugger.pause();
+                return self.send("S02");
+            }
+            return Ok(());
+        }
+        if packet.is_empty() || !packet.is_char_boundary(1) {
+            return self.send("");
+        }
+        let (command, args) = packet.split_at(1);
+        let reply = match command {
+            "?" => String::from("S05"),
+            "g" => (0..REGISTER_SIZES.len()).map(|n| read_register(chip8, n)).collect(),
+            "G" => {
+                let mut offset = 0;
+                for (n, &size) in REGISTER_SIZES.iter().enumerate() {
+                    match args.get(offset..offset + size * 2) {
+                        Some(value) => write_register(chip8, n, value),
+                        None => break,
+                    }
+                    offset += size * 2;
+                }
+                String::from("OK")
+            },
+            "p" => match usize::from_str_radix(args, 16) {
+                Ok(n) if n < REGISTER_SIZES.len() => read_register(chip8, n),
+                _ => String::from("E00"),
+            },
+            "P" => match args.split_once('=').map(|(n, value)| (usize::from_str_radix(n, 16), value)) {
+                Some((Ok(n), value)) if n < REGISTER_SIZES.len() && value.len() == REGISTER_SIZES[n] * 2 => {
+                    write_register(chip8, n, value);
+                    String::from("OK")
+                },
+                _ => String::from("E00"),
+            },
+            "m" => match parse_range(args).filter(|&(addr, len)| fits(addr, len, chip8.memory().len())) {
+                Some((addr, len)) => chip8.memory()[addr..addr + len].iter().map(|byte| format!("{:02x}", byte)).collect(),
+                None => String::from("E01"),
+            },
+            "M" => {
+                let (range, data) = args.split_once(':').unwrap_or((args, ""));
+                match (parse_range(range), decode_hex(data)) {
+                    (Some((addr, len)), Some(bytes)) if bytes.len() == len && fits(addr, len, chip8.memory().len()) => {
+                        chip8.memory_mut()[addr..addr + len].copy_from_slice(&bytes);
+                        String::from("OK")
+                    },
+                    _ => String::from("E01"),
+                }
+            },
+            "Z" | "z" => self.set_point(command == "Z", args),
+            "s" | "c" => {
+                if let Ok(addr) = u16::from_str_radix(args, 16) {
+                    chip8.set_pc(addr);
+                }
+                if command == "c" {
+                    self.debugger.resume();
+                    return Ok(());
+                }
+                match self.debugger.step(chip8) {
+                    Ok(()) if chip8.is_halted() => String::from("W00"),
+                    Ok(()) => String::from("S05"),
+                    Err(err) => String::from(fault_signal(err)),
+                }
+            },
+            "q" => query(args),
+            "H" => String::from("OK"),
+            "D" => {
+                self.send("OK")?;
+                self.disconnect();
+                self.debugger.resume();
+                return Ok(());
+            },
+            "k" => {
+                self.disconnect();
+                return Ok(());
+            },
+            _ => String::new(),
+        };
+        self.send(&reply)
+    }
+
+    // Z0 is a breakpoint, Z2, Z3 and Z4 are write, read and access
+    // watchpoints.
+    fn set_point(&mut self, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let kind = fields.next().and_then(|kind| kind.parse::<u8>().ok());
+        let addr = fields.next().and_then(|addr| usize::from_str_radix(addr, 16).ok());
+        let len = fields.next().and_then(|len| usize::from_str_radix(len, 16).ok()).unwrap_or(1);
+        let (kind, addr) = match (kind, addr) {
+            (Some(kind), Some(addr)) => (kind, addr),
+            _ => return String::from("E00"),
+        };
+        if !fits(addr, len, Chip8::XO_CHIP_MEMORY_SIZE) {
+            return String::from("E01");
+        }
+        let point = match kind {
+            0 => Point::Breakpoint { address: addr as u16, condition: None },
+            2 => Point::Watchpoint { address: addr, len, access: Access::Write },
+            3 => Point::Watchpoint { address: addr, len, access: Access::Read },
+            4 => Point::Watchpoint { address: addr, len, access: Access::ReadWrite },
+            _ => return String::new(),
+        };
+        if insert {
+            if !self.points.contains_key(&(kind, addr)) {
+                let id = self.debugger.add(point);
+                self.points.insert((kind, addr), id);
+            }
+        } else if let Some(id) = self.points.remove(&(kind, addr)) {
+            self.debugger.remove(id);
+        }
+        String::from("OK")
+    }
+
+    fn stop_reply(&self, stop: Stop) -> String {
+        match stop {
+            Stop::Breakpoint { .. } | Stop::Step => String::from("S05"),
+            Stop::Watchpoint { address, access, .. } => {
+                let kind = match access {
+                    Access::Write => "watch",
+                    Access::Read => "rwatch",
+                    Access::ReadWrite => "awatch",
+                };
+                format!("T05{}:{:x};", kind, address)
+            },
+            Stop::Halted => String::from("W00"),
+            Stop::Fault(err) => String::from(fault_signal(err)),
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        self.write_raw(format!("${}#{:02x}", data, checksum).as_bytes())
+    }
+
+    fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        client.set_nonblocking(false)?;
+        let result = client.write_all(bytes);
+        client.set_nonblocking(true)?;
+        result
+    }
+}
+
+fn query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        String::from("PacketSize=1000;qXfer:features:read+")
+    } else if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        match parse_range(range) {
+            Some((offset, len)) if offset < TARGET_XML.len() => {
+                let end = offset.saturating_add(len).min(TARGET_XML.len());
+                let more = if end < TARGET_XML.len() { "m" } else { "l" };
+                format!("{}{}", more, &TARGET_XML[offset..end])
+            },
+            Some(_) => String::from("l"),
+            None => String::from("E00"),
+        }
+    } else if args == "Attached" {
+        String::from("1")
+    } else if args == "C" {
+        String::from("QC1")
+    } else if args == "fThreadInfo" {
+        String::from("m1")
+    } else if args == "sThreadInfo" {
+        String::from("l")
+    } else {
+        String::new()
+    }
+}
+
+// SIGILL for unknown opcodes, SIGSEGV for bad memory and stack accesses.
+fn fault_signal(err: Chip8Error) -> &'static str {
+    match err {
+        Chip8Error::UnknownOpcode { .. } | Chip8Error::InvalidKey { .. } => "S04",
+        _ => "S0b",
+    }
+}
+
+fn read_register(chip8: &Chip8, n: usize) -> String {
+    match n {
+        0..=15 => format!("{:02x}", chip8.v()[n]),
+        16 => format!("{:04x}", chip8.i()),
+        17 => format!("{:04x}", chip8.pc()),
+        18 => format!("{:02x}", chip8.sp()),
+        19 => format!("{:02x}", chip8.delay()),
+        _ => format!("{:02x}", chip8.sound()),
+    }
+}
+
+fn write_register(chip8: &mut Chip8, n: usize, value: &str) {
+    let value = match u16::from_str_radix(value, 16) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    match n {
+        0..=15 => chip8.set_v(n, value as u8),
+        16 => chip8.set_i(value),
+        17 => chip8.set_pc(value),
+        18 => chip8.set_sp(value),
+        19 => chip8.set_delay(value as u8),
+        _ => chip8.set_sound(value as u8),
+    }
+}
+
+// Whether `len` bytes at `addr` are within `size` bytes of memory.
+fn fits(addr: usize, len: usize, size: usize) -> bool {
+    addr.checked_add(len).is_some_and(|end| end <= size)
+}
+
+// "addr,len" in hex.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (addr, len) = range.split_once(',')?;
+    Some((usize::from_str_radix(addr, 16).ok()?, usize::from_str_radix(len, 16).ok()?))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+
+// A decoded CHIP-8, SUPER-CHIP or XO-CHIP instruction. Variant names follow
+// the mnemonics from Cowgod's technical reference, register operands are
+// register numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 0nnn - SYS addr
+    Sys(u16),
+    // 00E0 - CLS
+    Cls,
+    // 00EE - RET
+    Ret,
+    // 00Cn - SCD nibble (SCHIP)
+    Scd(u8),
+    // 00Dn - SCU nibble (XO-CHIP)
+    Scu(u8),
+    // 00FB - SCR (SCHIP)
+    Scr,
+    // 00FC - SCL (SCHIP)
+    Scl,
+    // 00FD - EXIT (SCHIP)
+    Exit,
+    // 00FE - LOW (SCHIP)
+    Low,
+    // 00FF - HIGH (SCHIP)
+    High,
+    // 1nnn - JP addr
+    Jp(u16),
+    // 2nnn - CALL addr
+    Call(u16),
+    // 3xkk - SE Vx, byte
+    SeVxByte { x: u8, kk: u8 },
+    // 4xkk - SNE Vx, byte
+    SneVxByte { x: u8, kk: u8 },
+    // 5xy0 - SE Vx, Vy
+    SeVxVy { x: u8, y: u8 },
+    // 5xy2 - SAVE Vx, Vy (XO-CHIP)
+    Save { x: u8, y: u8 },
+    // 5xy3 - LOAD Vx, Vy (XO-CHIP)
+    Load { x: u8, y: u8 },
+    // 6xkk - LD Vx, byte
+    LdVxByte { x: u8, kk: u8 },
+    // 7xkk - ADD Vx, byte
+    AddVxByte { x: u8, kk: u8 },
+    // 8xy0 - LD Vx, Vy
+    LdVxVy { x: u8, y: u8 },
+    // 8xy1 - OR Vx, Vy
+    Or { x: u8, y: u8 },
+    // 8xy2 - AND Vx, Vy
+    And { x: u8, y: u8 },
+    // 8xy3 - XOR Vx, Vy
+    Xor { x: u8, y: u8 },
+    // 8xy4 - ADD Vx, Vy
+    AddVxVy { x: u8, y: u8 },
+    // 8xy5 - SUB Vx, Vy
+    Sub { x: u8, y: u8 },
+    // 8xy6 - SHR Vx {, Vy}
+    Shr { x: u8, y: u8 },
+    // 8xy7 - SUBN Vx, Vy
+    Subn { x: u8, y: u8 },
+    // 8xyE - SHL Vx {, Vy}
+    Shl { x: u8, y: u8 },
+    // 9xy0 - SNE Vx, Vy
+    SneVxVy { x: u8, y: u8 },
+    // Annn - LD I, addr
+    LdI(u16),
+    // Bnnn - JP V0, addr
+    JpV0(u16),
+    // Cxkk - RND Vx, byte
+    Rnd { x: u8, kk: u8 },
+    // Dxyn - DRW Vx, Vy, nibble, Dxy0 draws a 16x16 sprite in SCHIP
+    Drw { x: u8, y: u8, n: u8 },
+    // F000 nnnn - LD I, LONG addr (XO-CHIP), the only four byte instruction
+    LdILong(u16),
+    // Fn01 - PLANE nibble (XO-CHIP)
+    Plane(u8),
+    // F002 - AUDIO (XO-CHIP)
+    Audio,
+    // Ex9E - SKP Vx
+    Skp { x: u8 },
+    // ExA1 - SKNP Vx
+    Sknp { x: u8 },
+    // Fx07 - LD Vx, DT
+    LdVxDt { x: u8 },
+    // Fx0A - LD Vx, K
+    LdVxK { x: u8 },
+    // Fx15 - LD DT, Vx
+    LdDtVx { x: u8 },
+    // Fx18 - LD ST, Vx
+    LdStVx { x: u8 },
+    // Fx1E - ADD I, Vx
+    AddIVx { x: u8 },
+    // Fx29 - LD F, Vx
+    LdFVx { x: u8 },
+    // Fx30 - LD HF, Vx (SCHIP)
+    LdHfVx { x: u8 },
+    // Fx3A - PITCH Vx (XO-CHIP)
+    Pitch { x: u8 },
+    // Fx33 - LD B, Vx
+    LdBVx { x: u8 },
+    // Fx55 - LD [I], Vx
+    LdIVx { x: u8 },
+    // Fx65 - LD Vx, [I]
+    LdVxI { x: u8 },
+    // Fx75 - LD R, Vx (SCHIP)
+    LdRVx { x: u8 },
+    // Fx85 - LD Vx, R (SCHIP)
+    LdVxR { x: u8 },
+}
+
+// Decodes the instruction starting at `addr`, including the four byte
+// F000 nnnn. Returns `None` past the end of memory or for unknown opcodes.
+pub fn decode_at(memory: &[u8], addr: usize) -> Option<Instruction> {
+    let word = |addr: usize| Some(u16::from(*memory.get(addr)?) << 8 | u16::from(*memory.get(addr + 1)?));
+    match word(addr)? {
+        0xf000 => Some(Instruction::LdILong(word(addr + 2)?)),
+        opcode => decode(opcode),
+    }
+}
+
+// Decodes a raw big-endian opcode. Returns `None` for opcodes the
+// emulator does not implement and for F000, whose address lives in the
+// following word, see `decode_at`.
+pub fn decode(opcode: u16) -> Option<Instruction> {
+    use self::Instruction::*;
+
+    let x = ((opcode & 0x0f00) >> 8) as u8;
+    let y = ((opcode & 0x00f0) >> 4) as u8;
+    let n = (opcode & 0x000f) as u8;
+    let kk = (opcode & 0x00ff) as u8;
+    let nnn = opcode & 0x0fff;
+
+    let instruction = match opcode >> 12 {
+        0x0 => match opcode {
+            0x00e0 => Cls,
+            0x00ee => Ret,
+            0x00c0..=0x00cf => Scd(n),
+            0x00d0..=0x00df => Scu(n),
+            0x00fb => Scr,
+            0x00fc => Scl,
+            0x00fd => Exit,
+            0x00fe => Low,
+            0x00ff => High,
+            _ => Sys(nnn),
+        },
+        0x1 => Jp(nnn),
+        0x2 => Call(nnn),
+        0x3 => SeVxByte { x, kk },
+        0x4 => SneVxByte { x, kk },
+        0x5 => match n {
+            0x0 => SeVxVy { x, y },
+            0x2 => Save { x, y },
+            0x3 => Load { x, y },
+            _ => return None,
+        },
+        0x6 => LdVxByte { x, kk },
+        0x7 => AddVxByte { x, kk },
+        0x8 => match n {
+            0x0 => LdVxVy { x, y },
+            0x1 => Or { x, y },
+            0x2 => And { x, y },
+            0x3 => Xor { x, y },
+            0x4 => AddVxVy { x, y },
+            0x5 => Sub { x, y },
+            0x6 => Shr { x, y },
+            0x7 => Subn { x, y },
+            0xE => Shl { x, y },
+            _ => return None,
+        },
+        0x9 => SneVxVy { x, y },
+        0xA => LdI(nnn),
+        0xB => JpV0(nnn),
+        0xC => Rnd { x, kk },
+        0xD => Drw { x, y, n },
+        0xE => match kk {
+            0x9E => Skp { x },
+            0xA1 => Sknp { x },
+            _ => return None,
+        },
+        0xF => match kk {
+            0x00 => return None,
+            0x01 => Plane(x),
+            0x02 if x == 0 => Audio,
+            0x07 => LdVxDt { x },
+            0x0A => LdVxK { x },
+            0x15 => LdDtVx { x },
+            0x18 => LdStVx { x },
+            0x1E => AddIVx { x },
+            0x29 => LdFVx { x },
+            0x30 => LdHfVx { x },
+            0x33 => LdBVx { x },
+            0x3A => Pitch { x },
+            0x55 => LdIVx { x },
+            0x65 => LdVxI { x },
+            0x75 => LdRVx { x },
+            0x85 => LdVxR { x },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(instruction)
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Instruction::*;
+
+        match *self {
+            Sys(nnn) => write!(f, "SYS {:#05x}", nnn),
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            Scd(n) => write!(f, "SCD {}", n),
+            Scu(n) => write!(f, "SCU {}", n),
+            Scr => write!(f, "SCR"),
+            Scl => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            Low => write!(f, "LOW"),
+            High => write!(f, "HIGH"),
+            Jp(nnn) => write!(f, "JP {:#05x}", nnn),
+            Call(nnn) => write!(f, "CALL {:#05x}", nnn),
+            SeVxByte { x, kk } => write!(f, "SE V{:X}, {:#04x}", x, kk),
+            SneVxByte { x, kk } => write!(f, "SNE V{:X}, {:#04x}", x, kk),
+            SeVxVy { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Save { x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Load { x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            LdVxByte { x, kk } => write!(f, "LD V{:X}, {:#04x}", x, kk),
+            AddVxByte { x, kk } => write!(f, "ADD V{:X}, {:#04x}", x, kk),
+            LdVxVy { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AddVxVy { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Subn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SneVxVy { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LdI(nnn) => write!(f, "LD I, {:#05x}", nnn),
+            JpV0(nnn) => write!(f, "JP V0, {:#05x}", nnn),
+            Rnd { x, kk } => write!(f, "RND V{:X}, {:#04x}", x, kk),
+            Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            LdILong(nnnn) => write!(f, "LD I, LONG {:#06x}", nnnn),
+            Plane(n) => write!(f, "PLANE {}", n),
+            Audio => write!(f, "AUDIO"),
+            Pitch { x } => write!(f, "PITCH V{:X}", x),
+            Skp { x } => write!(f, "SKP V{:X}", x),
+            Sknp { x } => write!(f, "SKNP V{:X}", x),
+            LdVxDt { x } => write!(f, "LD V{:X}, DT", x),
+            LdVxK { x } => write!(f, "LD V{:X}, K", x),
+            LdDtVx { x } => write!(f, "LD DT, V{:X}", x),
+            LdStVx { x } => write!(f, "LD ST, V{:X}", x),
+            AddIVx { x } => write!(f, "ADD I, V{:X}", x),
+            LdFVx { x } => write!(f, "LD F, V{:X}", x),
+            LdHfVx { x } => write!(f, "LD HF, V{:X}", x),
+            LdBVx { x } => write!(f, "LD B, V{:X}", x),
+            LdIVx { x } => write!(f, "LD [I], V{:X}", x),
+            LdVxI { x } => write!(f, "LD V{:X}, [I]", x),
+            LdRVx { x } => write!(f, "LD R, V{:X}", x),
+            LdVxR { x } => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
+
+impl Instruction {
+    // Size of the instruction in memory.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::LdILong(_) => 4,
+            _ => 2,
+        }
+    }
+
+    // Whether the instruction only exists in SUPER-CHIP and XO-CHIP. Dxy0
+    // exists everywhere, but only draws a 16x16 sprite there.
+    pub fn is_super_chip(&self) -> bool {
+        use self::Instruction::*;
+
+        matches!(self, Scd(_) | Scr | Scl | Exit | Low | High | LdHfVx { .. } | LdRVx { .. } | LdVxR { .. })
+    }
+
+    // Whether the instruction only exists in XO-CHIP.
+    pub fn is_xo_chip(&self) -> bool {
+        use self::Instruction::*;
+
+        matches!(self, Scu(_) | Save { .. } | Load { .. } | LdILong(_) | Plane(_) | Audio | Pitch { .. })
+    }
+
+    // Encodes the instruction as it is laid out in memory.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let opcode = self.encode();
+        let mut bytes = vec![(opcode >> 8) as u8, opcode as u8];
+        if let Instruction::LdILong(nnnn) = self {
+            bytes.push((nnnn >> 8) as u8);
+            bytes.push(*nnnn as u8);
+        }
+        bytes
+    }
+
+    // Encodes the instruction back into its opcode, the inverse of `decode`.
+    // For F000 nnnn only the first word is returned.
+    pub fn encode(&self) -> u16 {
+        use self::Instruction::*;
+
+        let xy = |op: u16, x: u8, y: u8, n: u16| op << 12 | u16::from(x & 0xf) << 8 | u16::from(y & 0xf) << 4 | n;
+        let xkk = |op: u16, x: u8, kk: u8| op << 12 | u16::from(x & 0xf) << 8 | u16::from(kk);
+        let nnn = |op: u16, nnn: u16| op << 12 | (nnn & 0x0fff);
+
+        match *self {
+            Sys(addr) => nnn(0x0, addr),
+            Cls => 0x00e0,
+            Ret => 0x00ee,
+            Scd(n) => 0x00c0 | u16::from(n & 0xf),
+            Scu(n) => 0x00d0 | u16::from(n & 0xf),
+            Scr => 0x00fb,
+            Scl => 0x00fc,
+            Exit => 0x00fd,
+            Low => 0x00fe,
+            High => 0x00ff,
+            Jp(addr) => nnn(0x1, addr),
+            Call(addr) => nnn(0x2, addr),
+            SeVxByte { x, kk } => xkk(0x3, x, kk),
+            SneVxByte { x, kk } => xkk(0x4, x, kk),
+            SeVxVy { x, y } => xy(0x5, x, y, 0x0),
+            Save { x, y } => xy(0x5, x, y, 0x2),
+            Load { x, y } => xy(0x5, x, y, 0x3),
+            LdVxByte { x, kk } => xkk(0x6, x, kk),
+            AddVxByte { x, kk } => xkk(0x7, x, kk),
+            LdVxVy { x, y } => xy(0x8, x, y, 0x0),
+            Or { x, y } => xy(0x8, x, y, 0x1),
+            And { x, y } => xy(0x8, x, y, 0x2),
+            Xor { x, y } => xy(0x8, x, y, 0x3),
+            AddVxVy { x, y } => xy(0x8, x, y, 0x4),
+            Sub { x, y } => xy(0x8, x, y, 0x5),
+            Shr { x, y } => xy(0x8, x, y, 0x6),
+            Subn { x, y } => xy(0x8, x, y, 0x7),
+            Shl { x, y } => xy(0x8, x, y, 0xE),
+            SneVxVy { x, y } => xy(0x9, x, y, 0x0),
+            LdI(addr) => nnn(0xA, addr),
+            JpV0(addr) => nnn(0xB, addr),
+            Rnd { x, kk } => xkk(0xC, x, kk),
+            Drw { x, y, n } => xy(0xD, x, y, u16::from(n & 0xf)),
+            LdILong(_) => 0xf000,
+            Plane(n) => xkk(0xF, n, 0x01),
+            Audio => 0xf002,
+            Pitch { x } => xkk(0xF, x, 0x3A),
+            Skp { x } => xkk(0xE, x, 0x9E),
+            Sknp { x } => xkk(0xE, x, 0xA1),
+            LdVxDt { x } => xkk(0xF, x, 0x07),
+            LdVxK { x } => xkk(0xF, x, 0x0A),
+            LdDtVx { x } => xkk(0xF, x, 0x15),
+            LdStVx { x } => xkk(0xF, x, 0x18),
+            AddIVx { x } => xkk(0xF, x, 0x1E),
+            LdFVx { x } => xkk(0xF, x, 0x29),
+            LdHfVx { x } => xkk(0xF, x, 0x30),
+            LdBVx { x } => xkk(0xF, x, 0x33),
+            LdIVx { x } => xkk(0xF, x, 0x55),
+            LdVxI { x } => xkk(0xF, x, 0x65),
+            LdRVx { x } => xkk(0xF, x, 0x75),
+            LdVxR { x } => xkk(0xF, x, 0x85),
+        }
+    }
+}
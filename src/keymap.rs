@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+// What a physical key does in the frontends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Keypad(u8),
+    Pause,
+    Step,
+    Quit,
+    Screenshot,
+    RecordGif,
+    Faster,
+    Slower,
+    // Held, unlike the other hotkeys.
+    FastForward,
+    SlowMotion,
+}
+
+impl Action {
+    // Hotkey names as used in the `[hotkeys]` config table.
+    pub const HOTKEYS: [(&'static str, Action); 9] = [
+        ("pause", Action::Pause),
+        ("step", Action::Step),
+        ("quit", Action::Quit),
+        ("screenshot", Action::Screenshot),
+        ("gif", Action::RecordGif),
+        ("faster", Action::Faster),
+        ("slower", Action::Slower),
+        ("fast_forward", Action::FastForward),
+        ("slow_motion", Action::SlowMotion),
+    ];
+}
+
+// Maps physical keys to actions. Keys are identified by lowercase names such
+// as `q`, `4`, `space` or `left`, or by decimal scancodes when
+// `uses_scancodes` is set; frontends translate their key events to the same
+// names. Several keys can trigger one action, but each key does only one
+// thing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    scancodes: bool,
+    bindings: HashMap<String, Action>,
+}
+
+impl Keymap {
+    // The default layout:
+    //
+    //  Real keys |  Chip8
+    //  1 2 3 4   |  1 2 3 C
+    //  Q W E R   |  4 5 6 D
+    //  A S D F   |  7 8 9 E
+    //  Z X C V   |  A 0 B F
+    //
+    // with Space pausing, P stepping, Esc quitting, F12 taking a screenshot
+    // and F11 starting and stopping a GIF recording. Page Up and Page Down
+    // change the speed, Tab fast-forwards while held and M toggles slow
+    // motion.
+    pub fn new() -> Keymap {
+        let mut keymap = Keymap { scancodes: false, bindings: HashMap::new() };
+        keymap.bind_defaults();
+        keymap
+    }
+
+    fn bind_defaults(&mut self) {
+        const KEYPAD: [(&str, u8); 16] = [
+            ("1", 0x1), ("2", 0x2), ("3", 0x3), ("4", 0xC),
+            ("q", 0x4), ("w", 0x5), ("e", 0x6), ("r", 0xD),
+            ("a", 0x7), ("s", 0x8), ("d", 0x9), ("f", 0xE),
+            ("z", 0xA), ("x", 0x0), ("c", 0xB), ("v", 0xF),
+        ];
+        for &(name, key) in KEYPAD.iter() {
+            self.bind(Action::Keypad(key), &[name]);
+        }
+        self.bind(Action::Pause, &["space"]);
+        self.bind(Action::Step, &["p"]);
+        self.bind(Action::Quit, &["escape"]);
+        self.bind(Action::Screenshot, &["f12"]);
+        self.bind(Action::RecordGif, &["f11"]);
+        self.bind(Action::Faster, &["pageup"]);
+        self.bind(Action::Slower, &["pagedown"]);
+        self.bind(Action::FastForward, &["tab"]);
+        self.bind(Action::SlowMotion, &["m"]);
+    }
+
+    pub fn action(&self, key: &str) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+
+    // Keys bound to `action`, sorted by name.
+    pub fn keys(&self, action: Action) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.bindings.iter()
+            .filter(|&(_, &bound)| bound == action)
+            .map(|(key, _)| &key[..])
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    // Replaces the keys of `action`. The keys are taken away from whatever
+    // they did before.
+    pub fn bind<S: AsRef<str>>(&mut self, action: Action, keys: &[S]) {
+        self.bindings.retain(|_, bound| *bound != action);
+        for key in keys {
+            self.bindings.insert(key.as_ref().to_lowercase(), action);
+        }
+    }
+
+    pub fn uses_scancodes(&self) -> bool {
+        self.scancodes
+    }
+
+    // Scancode mode keeps bindings on the same physical keys whatever the
+    // keyboard layout, the bindings then have to be scancodes too. Switching
+    // drops the bindings of the other kind: scancode mode starts with no keys
+    // bound, key names get the default layout back.
+    pub fn set_scancodes(&mut self, scancodes: bool) {
+        if scancodes == self.scancodes {
+            return;
+        }
+        self.scancodes = scancodes;
+        self.bindings.clear();
+        if !scancodes {
+            self.bind_defaults();
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new()
+    }
+}
@@ -0,0 +1,40 @@
+pub mod asm;
+pub mod audio;
+pub mod capture;
+pub mod chip8;
+pub mod config;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod frame;
+pub mod gdb;
+pub mod instruction;
+pub mod keymap;
+pub mod movie;
+pub mod palette;
+pub mod phosphor;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod speed;
+pub mod state;
+pub mod trace;
+
+pub use audio::{Audio, AudioBackend, Beeper, NullAudio, Tone, WavAudio, Waveform};
+pub use capture::GifRecorder;
+pub use chip8::Chip8;
+pub use config::{Config, ConfigError, DisplaySettings, SpeedSettings};
+pub use debugger::Debugger;
+pub use error::Chip8Error;
+pub use gdb::GdbStub;
+pub use instruction::{decode, Instruction};
+pub use keymap::{Action, Keymap};
+pub use movie::{Movie, MovieError, Playback};
+pub use palette::Palette;
+pub use phosphor::Phosphor;
+pub use quirks::Quirks;
+pub use rewind::Rewind;
+pub use rng::{Chip8Rng, RngSource};
+pub use speed::Speed;
+pub use state::StateError;
+pub use trace::{TraceFilter, TraceFormat, TraceRecord, Tracer};
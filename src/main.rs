@@ -1,30 +1,285 @@
 use std::io;
 use std::io::prelude::*;
+use std::fs;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::env;
 
-mod chip8;
-use chip8::Chip8;
+use chip8_emu::{
+    Audio, AudioBackend, Chip8, Config, Debugger, DisplaySettings, GdbStub, Movie, Palette, Quirks, Speed,
+    SpeedSettings, Tone, TraceFilter, TraceFormat, Tracer, WavAudio, Waveform,
+};
+
+mod window;
+use window::Frontend;
 
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let mut file_name = String::from("");
-    if args.len() == 2 {
-        file_name.push_str(&args[1][..]);
-    } else {
-        file_name.push_str("assets/input.ch8");
+    let mut file_name = String::from("assets/input.ch8");
+    let mut seed: Option<u64> = None;
+    let mut quirks: Option<Quirks> = None;
+    let mut xo_chip = false;
+    let mut rewind_frames: Option<usize> = None;
+    let mut record: Option<String> = None;
+    let mut play: Option<String> = None;
+    let mut debug = false;
+    let mut gdb_port: Option<u16> = None;
+    let mut trace: Option<String> = None;
+    let mut trace_format = TraceFormat::Text;
+    let mut trace_filter = TraceFilter::default();
+    let mut config_path: Option<String> = None;
+    let mut palette: Option<Palette> = None;
+    let mut foreground: Option<[u8; 3]> = None;
+    let mut background: Option<[u8; 3]> = None;
+    let mut scale: Option<u32> = None;
+    let mut fade: Option<u8> = None;
+    let mut waveform: Option<Waveform> = None;
+    let mut frequency: Option<f32> = None;
+    let mut volume: Option<f32> = None;
+    let mut wav: Option<String> = None;
+    let mut ips: Option<u32> = None;
+    let mut fast_forward: Option<u32> = None;
+    let mut slow_motion: Option<u32> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--seed" => seed = Some(parse_value(&arg, args.next())?),
+            "--quirks" => {
+                let name: String = parse_value(&arg, args.next())?;
+                quirks = Some(Quirks::from_name(&name).ok_or_else(|| io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown quirks preset {:?}, expected vip, chip48, schip, xochip or modern", name),
+                ))?);
+            },
+            "--xo" => xo_chip = true,
+            "--rewind" => {
+                let seconds: usize = parse_value(&arg, args.next())?;
+                let frames = seconds.checked_mul(Chip8::FRAME_RATE as usize);
+                rewind_frames = Some(frames.ok_or_else(|| invalid_input(format!("--rewind {} is too long", seconds)))?);
+            },
+            "--record" => record = Some(parse_value(&arg, args.next())?),
+            "--play" => play = Some(parse_value(&arg, args.next())?),
+            "--debug" => debug = true,
+            "--gdb" => gdb_port = Some(parse_value(&arg, args.next())?),
+            "--trace" => trace = Some(parse_value(&arg, args.next())?),
+            "--trace-format" => {
+                let name: String = parse_value(&arg, args.next())?;
+                trace_format = TraceFormat::from_name(&name)
+                    .ok_or_else(|| invalid_input(format!("unknown trace format {:?}, expected text or jsonl", name)))?;
+            },
+            "--trace-range" => {
+                let range: String = parse_value(&arg, args.next())?;
+                trace_filter.addresses = TraceFilter::parse_addresses(&range)
+                    .ok_or_else(|| invalid_input(format!("bad trace range {:?}, expected e.g. 200-2ff", range)))?;
+            },
+            "--trace-class" => {
+                let classes: String = parse_value(&arg, args.next())?;
+                trace_filter.classes = TraceFilter::parse_classes(&classes)
+                    .ok_or_else(|| invalid_input(format!("bad opcode classes {:?}, expected e.g. 1,2,d", classes)))?;
+            },
+            "--config" => config_path = Some(parse_value(&arg, args.next())?),
+            "--palette" => {
+                let name: String = parse_value(&arg, args.next())?;
+                palette = Some(Palette::from_name(&name)
+                    .ok_or_else(|| invalid_input(format!("unknown palette {:?}, expected classic, green, amber or lcd", name)))?);
+            },
+            "--fg" | "--bg" => {
+                let text: String = parse_value(&arg, args.next())?;
+                let color = Palette::parse_color(&text)
+                    .ok_or_else(|| invalid_input(format!("bad colour {:?}, expected e.g. 33ff66", text)))?;
+                if arg == "--fg" {
+                    foreground = Some(color);
+                } else {
+                    background = Some(color);
+                }
+            },
+            "--scale" => {
+                let value: u32 = parse_value(&arg, args.next())?;
+                if value == 0 || value > DisplaySettings::MAX_SCALE {
+                    let max = DisplaySettings::MAX_SCALE;
+                    return Err(invalid_input(format!("--scale expects 1 to {}, got {}", max, value)));
+                }
+                scale = Some(value);
+            },
+            "--fade" => fade = Some(parse_value(&arg, args.next())?),
+            "--waveform" => {
+                let name: String = parse_value(&arg, args.next())?;
+                waveform = Some(Waveform::from_name(&name)
+                    .ok_or_else(|| invalid_input(format!("unknown waveform {:?}, expected square or sine", name)))?);
+            },
+            "--frequency" => frequency = Some(parse_frequency(&arg, args.next())?),
+            "--volume" => volume = Some(parse_volume(&arg, args.next())?),
+            "--wav" => wav = Some(parse_value(&arg, args.next())?),
+            "--ips" => {
+                let value: u32 = parse_value(&arg, args.next())?;
+                if value == 0 || value > SpeedSettings::MAX_IPS {
+                    let max = SpeedSettings::MAX_IPS;
+                    return Err(invalid_input(format!("--ips expects 1 to {}, got {}", max, value)));
+                }
+                ips = Some(value);
+            },
+            "--fast-forward" | "--slow-motion" => {
+                let value: u32 = parse_value(&arg, args.next())?;
+                let min = if arg == "--slow-motion" { 1 } else { 0 };
+                if value < min || value > SpeedSettings::MAX_FACTOR {
+                    let max = SpeedSettings::MAX_FACTOR;
+                    return Err(invalid_input(format!("{} expects {} to {}, got {}", arg, min, max, value)));
+                }
+                if arg == "--fast-forward" {
+                    fast_forward = Some(value);
+                } else {
+                    slow_motion = Some(value);
+                }
+            },
+            _ if !arg.starts_with("--") => file_name = arg,
+            _ => return Err(invalid_input(format!("unexpected argument {:?}", arg))),
+        }
+    }
+    // Debugger steps and breaks aren't part of a movie's input log.
+    if (debug || gdb_port.is_some()) && (record.is_some() || play.is_some()) {
+        return Err(invalid_input(String::from("--debug and --gdb can't be combined with --record or --play")));
     }
     let input_path_buf = env::current_dir().unwrap().join(Path::new(&file_name));
     let input_path = Path::new(&input_path_buf);
 
-    let mut f = File::open(input_path).unwrap();
+    let mut f = File::open(input_path)?;
     let mut rom = Vec::new();
     f.read_to_end(&mut rom)?;
 
-    let mut chip8 = Chip8::new(&rom);
+    // An explicit config file has to exist, the default one is optional.
+    let config = match config_path.map(PathBuf::from).or_else(|| Config::default_path().filter(|path| path.exists())) {
+        Some(path) => {
+            let text = fs::read_to_string(&path)?;
+            Config::parse(&text).map_err(|err| invalid_input(format!("{}: {}", path.display(), err)))?
+        },
+        None => Config::default(),
+    };
+    let rom_name = input_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    // The per-ROM rate from the config, unless given on the command line.
+    let mut speed = config.speed(&rom_name, &rom);
+    speed.ips = ips.unwrap_or(speed.ips);
+    speed.fast_forward = fast_forward.unwrap_or(speed.fast_forward);
+    speed.slow_motion = slow_motion.unwrap_or(speed.slow_motion);
+
+    // A played movie brings its own seed and configuration.
+    let movie = match &play {
+        Some(path) => Some(Movie::parse(&fs::read_to_string(path)?).map_err(invalid_data)?),
+        None => None,
+    };
+    let mut chip8 = match &movie {
+        Some(movie) => movie.start(&rom).map_err(invalid_data)?,
+        None => {
+            let mut chip8 = match seed {
+                Some(seed) => Chip8::with_seed(&rom, seed),
+                None => Chip8::new(&rom),
+            };
+            if xo_chip {
+                chip8.set_xo_chip(true);
+            }
+            let default_quirks = if xo_chip { Quirks::XO_CHIP } else { Quirks::default() };
+            chip8.set_quirks(quirks.unwrap_or(default_quirks));
+            chip8.set_instructions_per_second(speed.ips);
+            chip8
+        },
+    };
+    // Rerun with --seed to reproduce the same RND sequence.
+    if let Some(seed) = chip8.rng_seed() {
+        println!("RNG seed: {}", seed);
+    }
+
+    if let Some(path) = &trace {
+        let output = Box::new(io::BufWriter::new(File::create(path)?));
+        let mut tracer = Tracer::new(output, trace_format);
+        tracer.set_filter(trace_filter);
+        chip8.set_tracer(Some(tracer));
+    }
 
-    chip8.run();
+    let recording = match record {
+        Some(path) => Some((path, Movie::new(&rom, &chip8).map_err(invalid_data)?)),
+        None => None,
+    };
+
+
+    let mut frontend = Frontend::new(chip8, input_path);
+    frontend.set_keymap(config.keymap(&rom_name, &rom));
+    // Command line options win over the config file.
+    let mut display = config.display;
+    if let Some(palette) = palette {
+        display.palette = palette;
+    }
+    if let Some(color) = foreground {
+        display.palette.set_foreground(color);
+    }
+    if let Some(color) = background {
+        display.palette.set_background(color);
+    }
+    display.scale = scale.unwrap_or(display.scale);
+    display.fade_frames = fade.unwrap_or(display.fade_frames);
+    frontend.set_display(display);
+    frontend.set_speed(Speed::new(speed));
+    let mut tone = config.audio;
+    tone.waveform = waveform.unwrap_or(tone.waveform);
+    tone.frequency = frequency.unwrap_or(tone.frequency);
+    tone.volume = volume.unwrap_or(tone.volume);
+    // With --wav the sound goes to the file instead of the device.
+    let backend: Box<dyn AudioBackend> = match &wav {
+        Some(path) => Box::new(WavAudio::create(path)?),
+        None => window::device_audio(),
+    };
+    frontend.set_audio(Audio::new(tone, backend));
+    if let Some(frames) = rewind_frames {
+        frontend.set_rewind_depth(frames);
+    }
+    if let Some(movie) = &movie {
+        frontend.play(movie);
+    }
+    if let Some((path, recording)) = recording {
+        frontend.record(recording, Path::new(&path));
+    }
+    if debug {
+        frontend.attach_debugger(Debugger::new());
+    }
+    if let Some(port) = gdb_port {
+        frontend.attach_gdb(GdbStub::bind(("127.0.0.1", port))?);
+    }
+    if let Err(err) = frontend.run() {
+        return Err(io::Error::other(err));
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn parse_frequency(flag: &str, value: Option<String>) -> io::Result<f32> {
+    let frequency: f32 = parse_value(flag, value)?;
+    if !(Tone::MIN_FREQUENCY..=Tone::MAX_FREQUENCY).contains(&frequency) {
+        return Err(invalid_input(format!("{} expects 20 to 20000 Hz, got {}", flag, frequency)));
+    }
+    Ok(frequency)
+}
+
+fn parse_volume(flag: &str, value: Option<String>) -> io::Result<f32> {
+    let volume: f32 = parse_value(flag, value)?;
+    if !(0.0..=1.0).contains(&volume) {
+        return Err(invalid_input(format!("{} expects 0 to 1, got {}", flag, volume)));
+    }
+    Ok(volume)
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> io::Result<T> {
+    match value.as_ref().map(|value| value.parse()) {
+        Some(Ok(value)) => Ok(value),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} expects a value, got {:?}", flag, value),
+        )),
+    }
+}
@@ -0,0 +1,227 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::quirks::Quirks;
+
+// A key press or release applied before frame `frame` is executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+// Recorded keypad input of a session together with everything needed to
+// replay it from power-on: the ROM, RNG seed and machine configuration.
+//
+// Movies are stored as text:
+//
+//     chip8-movie 2
+//     rom 5d3e0f8a7c2b1e44
+//     seed 42
+//     quirks 0b
+//     xo 0
+//     ips 540
+//     120 5 down
+//     126 5 up
+//
+// `quirks` is `Quirks::to_bits` in hex, `ips` the instructions per second.
+// Each event line is a frame number, a hex key and `down` or `up`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub xo_chip: bool,
+    pub instructions_per_second: u32,
+    pub events: Vec<KeyEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    // Only machines using the built-in seeded generator can be replayed.
+    NoSeed,
+    RomMismatch { expected: u64, found: u64 },
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::NoSeed => write!(f, "movies need a machine with a seeded RNG"),
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "movie was recorded with ROM {:016x}, this ROM is {:016x}",
+                expected, found,
+            ),
+            MovieError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+// 64-bit FNV-1a, enough to tell ROMs apart.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Movie {
+    const HEADER: &'static str = "chip8-movie 2";
+
+    // Starts an empty movie for `chip8`, which must be freshly created from
+    // `rom` and configured.
+    pub fn new(rom: &[u8], chip8: &Chip8) -> Result<Movie, MovieError> {
+        Ok(Movie {
+            rom_hash: rom_hash(rom),
+            seed: chip8.rng_seed().ok_or(MovieError::NoSeed)?,
+            quirks: chip8.quirks(),
+            xo_chip: chip8.is_xo_chip(),
+            instructions_per_second: chip8.instructions_per_second(),
+            events: Vec::new(),
+        })
+    }
+
+    // Records a key event at the current frame of `chip8`.
+    pub fn record(&mut self, chip8: &Chip8, key: u8, pressed: bool) {
+        self.events.push(KeyEvent { frame: chip8.frames(), key: key & 0xf, pressed });
+    }
+
+    // Creates a machine in the state the movie was recorded from.
+    pub fn start(&self, rom: &[u8]) -> Result<Chip8, MovieError> {
+        let found = rom_hash(rom);
+        if found != self.rom_hash {
+            return Err(MovieError::RomMismatch { expected: self.rom_hash, found });
+        }
+        let mut chip8 = Chip8::with_seed(rom, self.seed);
+        if self.xo_chip {
+            chip8.set_xo_chip(true);
+        }
+        chip8.set_quirks(self.quirks);
+        chip8.set_instructions_per_second(self.instructions_per_second);
+        Ok(chip8)
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let last_line = text.lines().count();
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        match lines.next() {
+            Some((_, Movie::HEADER)) => {},
+            Some((line, _)) => return Err(parse_error(line, &format!("not a {} file", Movie::HEADER))),
+            None => return Err(parse_error(1, "empty movie")),
+        }
+
+        let mut header = |name: &str| -> Result<(usize, String), MovieError> {
+            match lines.next() {
+                Some((line, text)) => match text.split_once(' ') {
+                    Some((key, value)) if key == name => Ok((line, value.trim().to_string())),
+                    _ => Err(parse_error(line, &format!("expected {}", name))),
+                },
+                None => Err(parse_error(last_line, &format!("missing {}", name))),
+            }
+        };
+        let (line, value) = header("rom")?;
+        let rom_hash = u64::from_str_radix(&value, 16).map_err(|_| parse_error(line, "bad ROM hash"))?;
+        let (line, value) = header("seed")?;
+        let seed = value.parse().map_err(|_| parse_error(line, "bad seed"))?;
+        let (line, value) = header("quirks")?;
+        let quirks = u8::from_str_radix(&value, 16).map_err(|_| parse_error(line, "bad quirks"))?;
+        let (line, value) = header("xo")?;
+        let xo_chip = match &value[..] {
+            "0" => false,
+            "1" => true,
+            _ => return Err(parse_error(line, "bad xo flag")),
+        };
+        let (line, value) = header("ips")?;
+        let instructions_per_second = value.parse().ok()
+            .filter(|&ips| ips > 0)
+            .ok_or_else(|| parse_error(line, "bad ips"))?;
+
+        let mut events = Vec::new();
+        for (line, text) in lines {
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            let event = match fields[..] {
+                [frame, key, state] => KeyEvent {
+                    frame: frame.parse().map_err(|_| parse_error(line, "bad frame"))?,
+                    key: match u8::from_str_radix(key, 16) {
+                        Ok(key) if key < 0x10 => key,
+                        _ => return Err(parse_error(line, "bad key")),
+                    },
+                    pressed: match state {
+                        "down" => true,
+                        "up" => false,
+                        _ => return Err(parse_error(line, "expected down or up")),
+                    },
+                },
+                _ => return Err(parse_error(line, "expected <frame> <key> <down|up>")),
+            };
+            if events.last().is_some_and(|last: &KeyEvent| last.frame > event.frame) {
+                return Err(parse_error(line, "events out of order"));
+            }
+            events.push(event);
+        }
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            quirks: Quirks::from_bits(quirks),
+            xo_chip,
+            instructions_per_second,
+            events,
+        })
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> MovieError {
+    MovieError::Parse { line, message: message.to_string() }
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", Movie::HEADER)?;
+        writeln!(f, "rom {:016x}", self.rom_hash)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "quirks {:02x}", self.quirks.to_bits())?;
+        writeln!(f, "xo {}", self.xo_chip as u8)?;
+        writeln!(f, "ips {}", self.instructions_per_second)?;
+        for event in &self.events {
+            writeln!(f, "{} {:x} {}", event.frame, event.key, if event.pressed { "down" } else { "up" })?;
+        }
+        Ok(())
+    }
+}
+
+// Feeds the events of a movie back into a machine started with
+// `Movie::start`. Call `apply` before every frame.
+pub struct Playback {
+    events: Vec<KeyEvent>,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(movie: &Movie) -> Playback {
+        Playback { events: movie.events.clone(), next: 0 }
+    }
+
+    // Applies every event recorded up to the current frame of `chip8`.
+    pub fn apply(&mut self, chip8: &mut Chip8) {
+        while let Some(event) = self.events.get(self.next) {
+            if event.frame > chip8.frames() {
+                break;
+            }
+            chip8.set_key(event.key, event.pressed);
+            self.next += 1;
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.events.len()
+    }
+}
@@ -0,0 +1,72 @@
+use crate::frame;
+
+// RGB colours of the background and the three combinations of the two
+// XO-CHIP planes: plane 1, plane 2 and both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 4],
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette { colors: frame::PALETTE };
+    pub const GREEN: Palette = Palette { colors: [[0, 16, 0], [51, 255, 102], [26, 170, 68], [13, 96, 38]] };
+    pub const AMBER: Palette = Palette { colors: [[20, 10, 0], [255, 176, 0], [190, 120, 0], [120, 72, 0]] };
+    // The greenish-grey of the HP-48 and Game Boy screens.
+    pub const LCD: Palette = Palette { colors: [[155, 188, 15], [15, 56, 15], [48, 98, 48], [139, 172, 15]] };
+
+    // Looks up a preset by name: "classic", "green", "amber" or "lcd".
+    pub fn from_name(name: &str) -> Option<Palette> {
+        match name {
+            "classic" => Some(Palette::CLASSIC),
+            "green" => Some(Palette::GREEN),
+            "amber" => Some(Palette::AMBER),
+            "lcd" => Some(Palette::LCD),
+            _ => None,
+        }
+    }
+
+    // Parses `rrggbb` or `#rrggbb`.
+    pub fn parse_color(text: &str) -> Option<[u8; 3]> {
+        let hex = text.strip_prefix('#').unwrap_or(text);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub fn set_background(&mut self, color: [u8; 3]) {
+        self.colors[0] = color;
+    }
+
+    // The colour of plane 1, which is all non-XO-CHIP games use.
+    pub fn foreground(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+
+    pub fn set_foreground(&mut self, color: [u8; 3]) {
+        self.colors[1] = color;
+    }
+
+    // Colour of a pixel with the plane bits `value`, mixed with the
+    // background by `level` from 0 (background) to 1 (full colour).
+    pub fn blend(&self, value: u8, level: f32) -> [u8; 3] {
+        let background = self.background();
+        let color = self.colors[usize::from(value & 0b11)];
+        let mut blended = [0; 3];
+        for (channel, (&from, &to)) in blended.iter_mut().zip(background.iter().zip(color.iter())) {
+            *channel = (f32::from(from) + (f32::from(to) - f32::from(from)) * level).round() as u8;
+        }
+        blended
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::CLASSIC
+    }
+}
@@ -0,0 +1,84 @@
+use crate::chip8::Chip8;
+
+// Emulates the afterglow of a CRT: a pixel that goes dark fades out over a
+// few frames instead of vanishing. Games that erase and redraw sprites with
+// XOR every frame flicker much less this way.
+pub struct Phosphor {
+    fade_frames: u8,
+    width: usize,
+    pixels: Vec<u8>,
+    // Frames since each pixel was last lit, and the plane bits it had then.
+    age: Vec<u8>,
+    value: Vec<u8>,
+    frame: u64,
+    fading: bool,
+}
+
+impl Phosphor {
+    // With `fade_frames` 0 pixels go dark at once.
+    pub fn new(fade_frames: u8) -> Phosphor {
+        Phosphor {
+            fade_frames,
+            width: 0,
+            pixels: Vec::new(),
+            age: Vec::new(),
+            value: Vec::new(),
+            frame: 0,
+            fading: false,
+        }
+    }
+
+    pub fn fade_frames(&self) -> u8 {
+        self.fade_frames
+    }
+
+    // Takes in the display of `chip8`. Unlit pixels age by the frames run
+    // since the last update, so nothing fades further while the machine is
+    // paused. A resolution switch starts over.
+    pub fn update(&mut self, chip8: &Chip8) {
+        chip8.framebuffer(&mut self.pixels);
+        if self.width != chip8.width() || self.age.len() != self.pixels.len() {
+            self.width = chip8.width();
+            self.age = vec![u8::MAX; self.pixels.len()];
+            self.value = vec![0; self.pixels.len()];
+        }
+        let elapsed = chip8.frames().saturating_sub(self.frame).min(u64::from(u8::MAX)) as u8;
+        self.frame = chip8.frames();
+        self.fading = false;
+        for ((&pixel, age), value) in self.pixels.iter().zip(&mut self.age).zip(&mut self.value) {
+            if pixel != 0 {
+                *age = 0;
+                *value = pixel;
+            } else {
+                *age = age.saturating_add(elapsed).max(1);
+                self.fading |= *age <= self.fade_frames;
+            }
+        }
+    }
+
+    // Whether running `chip8` on changes the picture even if its display
+    // didn't change, because pixels are still fading out.
+    pub fn is_fading(&self, chip8: &Chip8) -> bool {
+        self.fading && chip8.frames() != self.frame
+    }
+
+    // Plane bits and brightness from 0 to 1 of the pixel at `x`, `y` as of
+    // the last update.
+    pub fn pixel(&self, x: usize, y: usize) -> (u8, f32) {
+        let i = y * self.width + x;
+        let age = self.age[i];
+        if age == 0 {
+            (self.value[i], 1.0)
+        } else if age > self.fade_frames {
+            (0, 0.0)
+        } else {
+            let level = 1.0 - f32::from(age) / (f32::from(self.fade_frames) + 1.0);
+            (self.value[i], level)
+        }
+    }
+
+    // Forgets the history, e.g. after loading a state.
+    pub fn clear(&mut self) {
+        self.age.iter_mut().for_each(|age| *age = u8::MAX);
+    }
+}
@@ -0,0 +1,139 @@
+// Behaviours that differ between CHIP-8 interpreters. ROMs written for one
+// interpreter often misbehave on another, so each difference is a flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6/8xyE shift Vy into Vx instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    // Fx55/Fx65 leave I pointing past the last register stored or loaded.
+    pub load_store_increments_i: bool,
+    // Bnnn jumps to nnn + Vx, where x is the high nibble of nnn, instead of nnn + V0.
+    pub jump_uses_vx: bool,
+    // Sprites are clipped at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+    // 8xy1/8xy2/8xy3 reset VF to 0.
+    pub logic_resets_vf: bool,
+    // Dxyn waits for the next frame before execution continues, so at most
+    // one sprite is drawn per frame.
+    pub display_wait: bool,
+    // Fx55/Fx65 leave I pointing at the last register stored or loaded, i.e.
+    // advance it by x, not x + 1. Takes precedence over
+    // `load_store_increments_i`.
+    pub load_store_increments_i_by_x: bool,
+    // The SUPER-CHIP instructions 00Cn, 00FB-00FF, Fx30, Fx75 and Fx85 exist
+    // and Dxy0 draws a 16x16 sprite. Without them 00Cn and 00Fx are machine
+    // code calls, which are ignored, and Dxy0 draws nothing. XO-CHIP
+    // machines always have them.
+    pub super_chip: bool,
+}
+
+impl Quirks {
+    // The original COSMAC VIP interpreter.
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        clip_sprites: true,
+        logic_resets_vf: true,
+        display_wait: true,
+        load_store_increments_i_by_x: false,
+        super_chip: false,
+    };
+
+    // CHIP-48 on the HP-48 calculators, which SUPER-CHIP fixed to leave I
+    // alone in Fx55/Fx65.
+    pub const CHIP48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: true,
+        clip_sprites: true,
+        logic_resets_vf: false,
+        display_wait: false,
+        load_store_increments_i_by_x: true,
+        super_chip: false,
+    };
+
+    // SUPER-CHIP 1.1.
+    pub const SCHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: true,
+        clip_sprites: true,
+        logic_resets_vf: false,
+        display_wait: false,
+        load_store_increments_i_by_x: false,
+        super_chip: true,
+    };
+
+    // XO-CHIP as implemented by Octo.
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        clip_sprites: false,
+        logic_resets_vf: false,
+        display_wait: false,
+        load_store_increments_i_by_x: false,
+        super_chip: true,
+    };
+
+    // What most modern emulators and ROMs written for them assume.
+    pub const MODERN: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: false,
+        clip_sprites: false,
+        logic_resets_vf: false,
+        display_wait: false,
+        load_store_increments_i_by_x: false,
+        super_chip: true,
+    };
+
+    // Looks up a preset by name: "vip", "chip48", "schip", "xochip" or "modern".
+    pub fn from_name(name: &str) -> Option<Quirks> {
+        match &name.to_ascii_lowercase()[..] {
+            "vip" | "cosmac" | "cosmac-vip" => Some(Quirks::COSMAC_VIP),
+            "chip48" | "chip-48" => Some(Quirks::CHIP48),
+            "schip" | "superchip" => Some(Quirks::SCHIP),
+            "xochip" | "xo-chip" => Some(Quirks::XO_CHIP),
+            "modern" => Some(Quirks::MODERN),
+            _ => None,
+        }
+    }
+}
+
+impl Quirks {
+    // Packs the flags into one bit each, in declaration order starting at
+    // bit 0. Used by save states and movies.
+    pub fn to_bits(&self) -> u8 {
+        let flags = [
+            self.shift_uses_vy,
+            self.load_store_increments_i,
+            self.jump_uses_vx,
+            self.clip_sprites,
+            self.logic_resets_vf,
+            self.display_wait,
+            self.load_store_increments_i_by_x,
+            self.super_chip,
+        ];
+        flags.iter().rev().fold(0, |bits, &flag| bits << 1 | flag as u8)
+    }
+
+    pub fn from_bits(bits: u8) -> Quirks {
+        Quirks {
+            shift_uses_vy: bits & 1 != 0,
+            load_store_increments_i: bits & 1 << 1 != 0,
+            jump_uses_vx: bits & 1 << 2 != 0,
+            clip_sprites: bits & 1 << 3 != 0,
+            logic_resets_vf: bits & 1 << 4 != 0,
+            display_wait: bits & 1 << 5 != 0,
+            load_store_increments_i_by_x: bits & 1 << 6 != 0,
+            super_chip: bits & 1 << 7 != 0,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::MODERN
+    }
+}
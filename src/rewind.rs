@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+
+// Ring buffer of save states, one per frame. Once `depth` states are held
+// the oldest one is dropped for every new one.
+pub struct Rewind {
+    states: VecDeque<Vec<u8>>,
+    depth: usize,
+}
+
+impl Rewind {
+    pub fn new(depth: usize) -> Rewind {
+        Rewind { states: VecDeque::with_capacity(depth), depth }
+    }
+
+    // Records the current state of `chip8`. Does nothing with a depth of 0.
+    pub fn push(&mut self, chip8: &Chip8) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.states.len() == self.depth {
+            self.states.pop_front();
+        }
+        self.states.push_back(chip8.save_state());
+    }
+
+    // Restores the most recent snapshot and drops it from the buffer.
+    // Returns false once the buffer is exhausted.
+    pub fn rewind(&mut self, chip8: &mut Chip8) -> bool {
+        match self.states.pop_back() {
+            Some(state) => chip8.load_state(&state).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    // Number of frames that can currently be rewound.
+    pub fn frames(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
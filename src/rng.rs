@@ -0,0 +1,93 @@
+use rand::{Error, RngCore, SeedableRng};
+
+// Small seedable generator used for Cxkk. Its whole state is a single u64,
+// so it can be stored with the rest of the machine and replayed exactly.
+// The output function is xorshift64*, seeds are mixed with splitmix64 so
+// that nearby seeds give unrelated sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chip8Rng {
+    state: u64,
+}
+
+impl Chip8Rng {
+    pub fn seed_from(seed: u64) -> Chip8Rng {
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        // xorshift never leaves the all-zero state.
+        Chip8Rng::from_state(if z == 0 { 0x9e3779b97f4a7c15 } else { z })
+    }
+
+    pub fn from_state(state: u64) -> Chip8Rng {
+        Chip8Rng { state }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for Chip8Rng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Chip8Rng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> Chip8Rng {
+        Chip8Rng::seed_from(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Chip8Rng {
+        Chip8Rng::seed_from(seed)
+    }
+}
+
+// Random source of a `Chip8`. The built-in generator is part of the machine
+// state, a custom `RngCore` is opaque and can't be saved or replayed.
+pub enum RngSource {
+    Seeded { seed: u64, rng: Chip8Rng },
+    Custom(Box<dyn RngCore + Send>),
+}
+
+impl RngSource {
+    pub fn seeded(seed: u64) -> RngSource {
+        RngSource::Seeded { seed, rng: Chip8Rng::seed_from(seed) }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            RngSource::Seeded { seed, .. } => Some(*seed),
+            RngSource::Custom(_) => None,
+        }
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        match self {
+            RngSource::Seeded { rng, .. } => rng.next_u32() as u8,
+            RngSource::Custom(rng) => rng.next_u32() as u8,
+        }
+    }
+}
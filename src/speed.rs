@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use crate::chip8::Chip8;
+use crate::config::SpeedSettings;
+
+// Paces emulated frames to the wall clock. The machine always runs whole
+// frames of virtual time, so its timers stay at 60 Hz of emulated time;
+// fast-forward and slow motion only change how often a frame is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Speed {
+    settings: SpeedSettings,
+    fast_forward: bool,
+    slow_motion: bool,
+}
+
+impl Speed {
+    // One frame at normal speed, ~60 Hz.
+    pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+    pub fn new(settings: SpeedSettings) -> Speed {
+        Speed { settings, fast_forward: false, slow_motion: false }
+    }
+
+    pub fn is_fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    pub fn set_fast_forward(&mut self, fast_forward: bool) {
+        self.fast_forward = fast_forward;
+    }
+
+    pub fn is_slow_motion(&self) -> bool {
+        self.slow_motion
+    }
+
+    pub fn set_slow_motion(&mut self, slow_motion: bool) {
+        self.slow_motion = slow_motion;
+    }
+
+    // Wall time per frame, or `None` to run frames as fast as possible.
+    // Fast-forward wins over slow motion while it's held.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        if self.fast_forward {
+            match self.settings.fast_forward {
+                0 => None,
+                factor => Some(Speed::FRAME / factor),
+            }
+        } else if self.slow_motion {
+            Some(Speed::FRAME * self.settings.slow_motion.max(1))
+        } else {
+            Some(Speed::FRAME)
+        }
+    }
+
+    // Raises the instruction rate by about a quarter, at least one
+    // instruction per second.
+    pub fn faster(chip8: &mut Chip8) {
+        let ips = chip8.instructions_per_second();
+        chip8.set_instructions_per_second((ips + 1).max(ips * 5 / 4).min(SpeedSettings::MAX_IPS));
+    }
+
+    // Undoes `faster`, down to one instruction per second.
+    pub fn slower(chip8: &mut Chip8) {
+        let ips = chip8.instructions_per_second();
+        chip8.set_instructions_per_second((ips - 1).min(ips * 4 / 5));
+    }
+
+    // The instruction rate and fast-forward or slow-motion factor, for the
+    // window title and status lines, e.g. "720 IPS, slow motion 1/4".
+    pub fn describe(&self, chip8: &Chip8) -> String {
+        let ips = chip8.instructions_per_second();
+        if self.fast_forward {
+            match self.settings.fast_forward {
+                0 => format!("{} IPS, fast-forward", ips),
+                factor => format!("{} IPS, fast-forward {}x", ips, factor),
+            }
+        } else if self.slow_motion {
+            format!("{} IPS, slow motion 1/{}", ips, self.settings.slow_motion)
+        } else {
+            format!("{} IPS", ips)
+        }
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Speed {
+        Speed::new(SpeedSettings::default())
+    }
+}
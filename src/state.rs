@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::frame;
+
+// Save states start with this tag followed by a little-endian u16 format
+// version, see `Chip8::save_state` for the layout.
+pub const MAGIC: [u8; 4] = *b"CH8S";
+pub const VERSION: u16 = 3;
+
+// Reasons a save state can't be restored. The machine is left untouched
+// when loading fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    NotAState,
+    UnsupportedVersion { version: u16 },
+    Truncated,
+    Invalid { field: &'static str },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::NotAState => write!(f, "not a CHIP-8 save state"),
+            StateError::UnsupportedVersion { version } => write!(
+                f,
+                "save state version {} is not supported, this build reads version {}",
+                version, VERSION,
+            ),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid { field } => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+pub(crate) struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        let mut writer = StateWriter { data: Vec::new() };
+        writer.bytes(&MAGIC);
+        writer.u16(VERSION);
+        writer
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    // Checks the header and returns a reader positioned after it.
+    pub fn new(data: &'a [u8]) -> Result<StateReader<'a>, StateError> {
+        if data.len() < MAGIC.len() || data[..MAGIC.len()] != MAGIC {
+            return Err(StateError::NotAState);
+        }
+        let mut reader = StateReader { data: &data[MAGIC.len()..] };
+        match reader.u16()? {
+            VERSION => Ok(reader),
+            version => Err(StateError::UnsupportedVersion { version }),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid { field: "flag" }),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    // Fails if anything is left after the last field.
+    pub fn finish(self) -> Result<(), StateError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(StateError::Invalid { field: "length" })
+        }
+    }
+}
+
+// Human-readable JSON summary of the machine for test reports. Unlike
+// `Chip8::save_state` it can't be loaded back; memory is left out and the
+// framebuffer is reduced to its hash.
+pub fn to_json(chip8: &Chip8) -> String {
+    let quirks = chip8.quirks();
+    let seed = match chip8.rng_seed() {
+        Some(seed) => seed.to_string(),
+        None => String::from("null"),
+    };
+    let keys: Vec<u8> = (0..16).filter(|&key| chip8.keys()[usize::from(key)]).collect();
+    format!(
+        concat!(
+            "{{\n",
+            "  \"pc\": {},\n",
+            "  \"i\": {},\n",
+            "  \"v\": {:?},\n",
+            "  \"sp\": {},\n",
+            "  \"stack\": {:?},\n",
+            "  \"delay\": {},\n",
+            "  \"sound\": {},\n",
+            "  \"keys\": {:?},\n",
+            "  \"waiting_for_key\": {},\n",
+            "  \"halted\": {},\n",
+            "  \"cycles\": {},\n",
+            "  \"frames\": {},\n",
+            "  \"rng_seed\": {},\n",
+            "  \"hires\": {},\n",
+            "  \"xo_chip\": {},\n",
+            "  \"plane\": {},\n",
+            "  \"pitch\": {},\n",
+            "  \"quirks\": {{\"shift_uses_vy\": {}, \"load_store_increments_i\": {}, \"jump_uses_vx\": {}, ",
+            "\"clip_sprites\": {}, \"logic_resets_vf\": {}, \"display_wait\": {}, ",
+            "\"load_store_increments_i_by_x\": {}, \"super_chip\": {}}},\n",
+            "  \"framebuffer_hash\": \"{:016x}\"\n",
+            "}}\n",
+        ),
+        chip8.pc(),
+        chip8.i(),
+        chip8.v(),
+        chip8.sp(),
+        &chip8.stack()[..usize::from(chip8.sp())],
+        chip8.delay(),
+        chip8.sound(),
+        keys,
+        chip8.is_waiting(),
+        chip8.is_halted(),
+        chip8.cycles(),
+        chip8.frames(),
+        seed,
+        chip8.is_hires(),
+        chip8.is_xo_chip(),
+        chip8.plane(),
+        chip8.pitch(),
+        quirks.shift_uses_vy,
+        quirks.load_store_increments_i,
+        quirks.jump_uses_vx,
+        quirks.clip_sprites,
+        quirks.logic_resets_vf,
+        quirks.display_wait,
+        quirks.load_store_increments_i_by_x,
+        quirks.super_chip,
+        frame::hash(chip8),
+    )
+}
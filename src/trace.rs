@@ -0,0 +1,211 @@
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::io::prelude::*;
+use std::ops::RangeInclusive;
+
+use crate::instruction::Instruction;
+
+// One executed instruction with the registers before and after it. `pc`
+// is the address of the instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    pub v_before: [u8; 16],
+    pub i_before: u16,
+    pub sp_before: u16,
+    pub delay_before: u8,
+    pub sound_before: u8,
+    pub v_after: [u8; 16],
+    pub i_after: u16,
+    pub pc_after: u16,
+    pub sp_after: u16,
+    pub delay_after: u8,
+    pub sound_after: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    // One line per instruction with the registers that changed.
+    Text,
+    // One JSON object per line.
+    JsonLines,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<TraceFormat> {
+        match name {
+            "text" => Some(TraceFormat::Text),
+            "json" | "jsonl" => Some(TraceFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+// Selects which instructions are traced. The opcode class is the high
+// nibble of the opcode, so `classes` has one bit per class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFilter {
+    pub addresses: RangeInclusive<u16>,
+    pub classes: u16,
+}
+
+impl TraceFilter {
+    // Parses an inclusive hex range such as "200-2ff", or a single address.
+    pub fn parse_addresses(text: &str) -> Option<RangeInclusive<u16>> {
+        let (start, end) = text.split_once('-').unwrap_or((text, text));
+        let start = u16::from_str_radix(start.trim_start_matches("0x"), 16).ok()?;
+        let end = u16::from_str_radix(end.trim_start_matches("0x"), 16).ok()?;
+        Some(start..=end)
+    }
+
+    // Parses a comma-separated list of opcode classes such as "1,2,d".
+    pub fn parse_classes(text: &str) -> Option<u16> {
+        text.split(',').try_fold(0, |classes, class| match u8::from_str_radix(class.trim(), 16) {
+            Ok(class) if class < 0x10 => Some(classes | 1 << class),
+            _ => None,
+        })
+    }
+
+    pub fn matches(&self, pc: u16, opcode: u16) -> bool {
+        self.addresses.contains(&pc) && self.classes & 1 << (opcode >> 12) != 0
+    }
+}
+
+impl Default for TraceFilter {
+    fn default() -> TraceFilter {
+        TraceFilter { addresses: 0..=0xffff, classes: 0xffff }
+    }
+}
+
+// Writes a `TraceRecord` for every matching instruction executed by the
+// `Chip8` it is attached to. The first write error disables the tracer, it
+// is kept in `error`.
+pub struct Tracer {
+    output: Box<dyn Write + Send>,
+    format: TraceFormat,
+    filter: TraceFilter,
+    enabled: bool,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new(output: Box<dyn Write + Send>, format: TraceFormat) -> Tracer {
+        Tracer { output, format, filter: TraceFilter::default(), enabled: true, error: None }
+    }
+
+    pub fn filter(&self) -> &TraceFilter {
+        &self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: TraceFilter) {
+        self.filter = filter;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled && self.error.is_none()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.flush();
+        }
+    }
+
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn wants(&self, pc: u16, opcode: u16) -> bool {
+        self.is_enabled() && self.filter.matches(pc, opcode)
+    }
+
+    pub fn record(&mut self, record: &TraceRecord) {
+        if !self.is_enabled() {
+            return;
+        }
+        let line = match self.format {
+            TraceFormat::Text => text_line(record),
+            TraceFormat::JsonLines => json_line(record),
+        };
+        if let Err(err) = self.output.write_all(line.as_bytes()) {
+            self.error = Some(err);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(err) = self.output.flush() {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        self.output.flush().ok();
+    }
+}
+
+// `       42 0204 6103 LD V1, 0x03              V1=00->03`
+// PC is only shown when it doesn't move on to the next instruction.
+fn text_line(record: &TraceRecord) -> String {
+    let mut line = format!(
+        "{:9} {:04x} {:04x} {:<24}",
+        record.cycle,
+        record.pc,
+        record.opcode,
+        record.instruction.to_string(),
+    );
+    for (x, (before, after)) in record.v_before.iter().zip(record.v_after.iter()).enumerate() {
+        if before != after {
+            write!(line, " V{:X}={:02x}->{:02x}", x, before, after).unwrap();
+        }
+    }
+    if record.i_before != record.i_after {
+        write!(line, " I={:04x}->{:04x}", record.i_before, record.i_after).unwrap();
+    }
+    if record.pc_after != record.pc.wrapping_add(record.instruction.size() as u16) {
+        write!(line, " PC={:04x}->{:04x}", record.pc, record.pc_after).unwrap();
+    }
+    if record.sp_before != record.sp_after {
+        write!(line, " SP={:x}->{:x}", record.sp_before, record.sp_after).unwrap();
+    }
+    if record.delay_before != record.delay_after {
+        write!(line, " DT={:02x}->{:02x}", record.delay_before, record.delay_after).unwrap();
+    }
+    if record.sound_before != record.sound_after {
+        write!(line, " ST={:02x}->{:02x}", record.sound_before, record.sound_after).unwrap();
+    }
+    line.truncate(line.trim_end().len());
+    line.push('\n');
+    line
+}
+
+fn json_line(record: &TraceRecord) -> String {
+    let mnemonic = record.instruction.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+    format!(
+        concat!(
+            "{{\"cycle\":{},\"pc\":{},\"opcode\":{},\"mnemonic\":\"{}\",",
+            "\"v_before\":{:?},\"i_before\":{},\"sp_before\":{},\"dt_before\":{},\"st_before\":{},",
+            "\"v_after\":{:?},\"i_after\":{},\"pc_after\":{},\"sp_after\":{},\"dt_after\":{},\"st_after\":{}}}\n",
+        ),
+        record.cycle,
+        record.pc,
+        record.opcode,
+        mnemonic,
+        record.v_before,
+        record.i_before,
+        record.sp_before,
+        record.delay_before,
+        record.sound_before,
+        record.v_after,
+        record.i_after,
+        record.pc_after,
+        record.sp_after,
+        record.delay_after,
+        record.sound_after,
+    )
+}
@@ -0,0 +1,666 @@
+extern crate piston;
+extern crate piston_window;
+extern crate graphics;
+extern crate opengl_graphics;
+extern crate rodio;
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use opengl_graphics::{
+    CreateTexture, Filter, Format, GlGraphics, OpenGL, Texture as GlTexture, TextureSettings, UpdateTexture,
+};
+use piston_window::*;
+use chip8_emu::debugger::{self, Debugger};
+use chip8_emu::{
+    frame, Action, Audio, AudioBackend, Chip8, Chip8Error, DisplaySettings, GdbStub, GifRecorder, Keymap, Movie,
+    Phosphor, Playback, Rewind, Speed, TraceFormat, Tracer,
+};
+
+// Piston/rodio frontend driving a `Chip8` in real time. The core runs in
+// virtual time, the frontend only paces whole frames to the wall clock.
+pub struct Frontend {
+    chip8: Chip8,
+    next_frame: SystemTime,
+    speed: Speed,
+    pause: bool,
+    // Save slots are stored next to the ROM as `<rom>.state1` and so on.
+    rom_path: PathBuf,
+    shift: bool,
+    // Held frames are replayed backwards while Backspace is down.
+    rewind: Rewind,
+    rewinding: bool,
+    // Keypad input is either recorded to a movie file or replayed from one.
+    // Both need an uninterrupted run, so slots and rewind can't be loaded.
+    recording: Option<(PathBuf, Movie)>,
+    playback: Option<Playback>,
+    // Console commands are read from stdin on a separate thread.
+    debugger: Option<Debugger>,
+    console: Option<Receiver<String>>,
+    gdb: Option<GdbStub>,
+    keymap: Keymap,
+    display: DisplaySettings,
+    phosphor: Phosphor,
+    // Screenshots and GIFs are saved next to the ROM as `<rom>.<frame>.png`
+    // and `<rom>.<frame>.gif`, numbered by the frame they start at.
+    gif: Option<(PathBuf, GifRecorder<io::BufWriter<fs::File>>)>,
+    // Plays through the sound device unless set to something else.
+    audio: Option<Audio>,
+}
+
+impl Frontend {
+    // 10 seconds at 60 frames per second.
+    pub const DEFAULT_REWIND_FRAMES: usize = 600;
+    // Unlimited fast-forward runs frames for this long per update, leaving
+    // the rest of the time for input and drawing.
+    const FAST_FORWARD_SLICE: Duration = Duration::from_millis(10);
+
+    pub fn new(chip8: Chip8, rom_path: &Path) -> Frontend {
+        Frontend {
+            chip8,
+            next_frame: SystemTime::now(),
+            speed: Speed::default(),
+            pause: false,
+            rom_path: rom_path.to_path_buf(),
+            shift: false,
+            rewind: Rewind::new(Frontend::DEFAULT_REWIND_FRAMES),
+            rewinding: false,
+            recording: None,
+            playback: None,
+            debugger: None,
+            console: None,
+            gdb: None,
+            keymap: Keymap::new(),
+            display: DisplaySettings::default(),
+            phosphor: Phosphor::new(0),
+            gif: None,
+            audio: None,
+        }
+    }
+
+    // Hands run control to a remote gdb. The machine stays stopped until a
+    // client connects and continues it.
+    pub fn attach_gdb(&mut self, gdb: GdbStub) {
+        if let Ok(addr) = gdb.local_addr() {
+            println!("Waiting for gdb on {}", addr);
+        }
+        self.gdb = Some(gdb);
+    }
+
+    // Attaches a debugger console on stdin. The machine starts stopped.
+    pub fn attach_debugger(&mut self, debugger: Debugger) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        println!("Debugger attached, type help for a list of commands.");
+        print!("{}", debugger::location(&self.chip8));
+        Frontend::prompt();
+        self.debugger = Some(debugger);
+        self.console = Some(receiver);
+    }
+
+    // Records keypad input into `movie`, written to `path` when the window
+    // closes.
+    pub fn record(&mut self, movie: Movie, path: &Path) {
+        self.recording = Some((path.to_path_buf(), movie));
+    }
+
+    // Replays the input of `movie`, the machine must come from `Movie::start`.
+    pub fn play(&mut self, movie: &Movie) {
+        self.playback = Some(Playback::new(movie));
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    // Colours, window scale and fading, before `run`.
+    pub fn set_display(&mut self, display: DisplaySettings) {
+        self.phosphor = Phosphor::new(display.fade_frames);
+        self.display = display;
+    }
+
+    // The beeper and where it plays, before `run`.
+    pub fn set_audio(&mut self, audio: Audio) {
+        self.audio = Some(audio);
+    }
+
+    // Fast-forward and slow-motion factors. The instruction rate is the
+    // machine's own.
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    // Number of frames kept for rewinding, 0 disables it.
+    pub fn set_rewind_depth(&mut self, frames: usize) {
+        self.rewind = Rewind::new(frames);
+    }
+
+    pub fn run(&mut self) -> Result<(), Chip8Error> {
+        let result = self.run_window();
+        if self.gif.is_some() {
+            self.toggle_gif();
+        }
+        if let Some(audio) = &mut self.audio {
+            if let Err(err) = audio.finish() {
+                eprintln!("Can't finish the audio output: {}", err);
+            }
+        }
+        if let Some((path, movie)) = &self.recording {
+            match fs::write(path, movie.to_string()) {
+                Ok(()) => println!("Saved movie to {}", path.display()),
+                Err(err) => eprintln!("Can't save movie to {}: {}", path.display(), err),
+            }
+        }
+        result
+    }
+
+    fn run_window(&mut self) -> Result<(), Chip8Error> {
+        let opengl = OpenGL::V3_2;
+        let mut window: PistonWindow =
+            WindowSettings::new(self.title(), self.window_size()).graphics_api(opengl)
+            .exit_on_esc(false).build().unwrap();
+        window.set_ups(1000);
+        let gl = &mut GlGraphics::new(opengl);
+        // The display is drawn as one texture sized for hi-res mode, of which
+        // lo-res mode uses the top-left corner. Nearest-neighbour filtering
+        // keeps the pixels sharp at any scale.
+        let mut pixels = vec![0; Chip8::HIRES_WIDTH * Chip8::HIRES_HEIGHT * 4];
+        let mut texture: GlTexture = CreateTexture::create(
+            &mut (),
+            Format::Rgba8,
+            &pixels,
+            [Chip8::HIRES_WIDTH as u32, Chip8::HIRES_HEIGHT as u32],
+            &TextureSettings::new().filter(Filter::Nearest),
+        ).expect("can't create the display texture");
+        
+        if self.audio.is_none() {
+            self.audio = Some(Audio::new(Default::default(), device_audio()));
+        }
+        let mut title = self.title();
+
+        while let Some(event) = window.next() {
+            // With a debugger attached the window stays open for inspection.
+            if self.chip8.is_halted() && self.debugger.is_none() && self.gdb.is_none() {
+                break;
+            }
+            self.poll_console();
+            if let Some(gdb) = &mut self.gdb {
+                if let Err(err) = gdb.poll(&mut self.chip8) {
+                    eprintln!("gdb connection failed: {}", err);
+                }
+            }
+            if let Some(args) = event.button_args() {
+                use piston_window::Button::Keyboard;
+                let button_pressed = args.state == ButtonState::Press;
+                let action = match args.button {
+                    Keyboard(_) if self.keymap.uses_scancodes() => {
+                        args.scancode.and_then(|scancode| self.keymap.action(&scancode.to_string()))
+                    },
+                    Keyboard(key) => self.keymap.action(&key_name(key)),
+                    _ => None,
+                };
+                // F1-F9 load a save slot, with shift held they save to it.
+                let slot = match args.button {
+                    Keyboard(Key::F1) => 1,
+                    Keyboard(Key::F2) => 2,
+                    Keyboard(Key::F3) => 3,
+                    Keyboard(Key::F4) => 4,
+                    Keyboard(Key::F5) => 5,
+                    Keyboard(Key::F6) => 6,
+                    Keyboard(Key::F7) => 7,
+                    Keyboard(Key::F8) => 8,
+                    Keyboard(Key::F9) => 9,
+                    _ => 0,
+                };
+                match action {
+                    Some(Action::FastForward) => {
+                        self.speed.set_fast_forward(button_pressed);
+                        self.next_frame = SystemTime::now();
+                    },
+                    Some(Action::Keypad(key)) if self.playback.is_none() => {
+                        if let Some((_, movie)) = &mut self.recording {
+                            movie.record(&self.chip8, key, button_pressed);
+                        }
+                        self.chip8.set_key(key, button_pressed);
+                    },
+                    Some(Action::Keypad(_)) => {},
+                    Some(_) if !button_pressed => {},
+                    Some(Action::Pause) => self.toggle_pause(),
+                    Some(Action::Quit) => break,
+                    // Steps aren't in a movie's input log, so they'd desync the replay.
+                    Some(Action::Step) if self.is_movie_active() => {
+                        eprintln!("Can't step while a movie is recorded or played");
+                    },
+                    Some(Action::Step) if !self.chip8.is_waiting() => {
+                        if let Some(debugger) = &mut self.debugger {
+                            match debugger.step(&mut self.chip8) {
+                                Ok(()) => print!("{}", debugger::location(&self.chip8)),
+                                Err(err) => println!("fault: {}", err),
+                            }
+                            Frontend::prompt();
+                        } else {
+                            let result = self.chip8.step();
+                            self.check(result)?;
+                        }
+                    },
+                    Some(Action::Step) => {},
+                    Some(Action::Screenshot) => self.screenshot(),
+                    Some(Action::RecordGif) => self.toggle_gif(),
+                    // The instruction rate is part of a movie's configuration.
+                    Some(Action::Faster | Action::Slower) if self.is_movie_active() => {
+                        eprintln!("Can't change the speed while a movie is recorded or played");
+                    },
+                    Some(Action::Faster) => Speed::faster(&mut self.chip8),
+                    Some(Action::Slower) => Speed::slower(&mut self.chip8),
+                    Some(Action::SlowMotion) => {
+                        let slow_motion = !self.speed.is_slow_motion();
+                        self.speed.set_slow_motion(slow_motion);
+                        self.next_frame = SystemTime::now();
+                    },
+                    None if args.button == Keyboard(Key::LShift) || args.button == Keyboard(Key::RShift) => {
+                        self.shift = button_pressed;
+                    },
+                    None if args.button == Keyboard(Key::Backspace) && !self.is_movie_active() => {
+                        if button_pressed && !self.rewinding {
+                            self.next_frame = SystemTime::now();
+                        }
+                        self.rewinding = button_pressed;
+                    },
+                    None if slot > 0 && button_pressed => {
+                        if self.shift {
+                            self.save_slot(slot);
+                        } else if self.is_movie_active() {
+                            eprintln!("Can't load slot {} while a movie is recorded or played", slot);
+                        } else {
+                            self.load_slot(slot);
+                        }
+                    },
+                    None if args.button == Keyboard(Key::T) && button_pressed => self.toggle_trace(),
+                    None => {},
+                }
+            }
+
+            match self.speed.frame_duration() {
+                Some(duration) if self.is_running() && self.next_frame <= SystemTime::now() => {
+                    self.next_frame += duration;
+                    self.run_frame()?;
+                },
+                Some(_) => {},
+                None if event.update_args().is_some() => {
+                    let start = Instant::now();
+                    while self.is_running() && !self.chip8.is_halted() && start.elapsed() < Frontend::FAST_FORWARD_SLICE {
+                        self.run_frame()?;
+                    }
+                    self.next_frame = SystemTime::now();
+                },
+                None => {},
+            }
+            if self.title() != title {
+                title = self.title();
+                window.set_title(title.clone());
+            }
+
+            if let Some(args) = event.render_args() {
+                let (width, height) = (self.chip8.width(), self.chip8.height());
+                // The texture is only rebuilt when the picture changed.
+                if self.chip8.take_display_dirty() || self.phosphor.is_fading(&self.chip8) {
+                    self.phosphor.update(&self.chip8);
+                    pixels.clear();
+                    for y in 0..height {
+                        for x in 0..width {
+                            let (value, level) = self.phosphor.pixel(x, y);
+                            pixels.extend_from_slice(&self.display.palette.blend(value, level));
+                            pixels.push(0xff);
+                        }
+                    }
+                    UpdateTexture::update(&mut texture, &mut (), Format::Rgba8, &pixels, [0, 0], [width as u32, height as u32])
+                        .expect("can't update the display texture");
+                }
+                let [window_width, window_height] = self.window_size();
+                let background = color(self.display.palette.background());
+                gl.draw(args.viewport(), |context, graphics| {
+                    graphics::clear(background, graphics);
+                    graphics::Image::new()
+                        .src_rect([0.0, 0.0, width as f64, height as f64])
+                        .rect([0.0, 0.0, f64::from(window_width), f64::from(window_height)])
+                        .draw(&texture, &context.draw_state, context.transform, graphics);
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.rewinding || !self.pause
+    }
+
+    // Runs, or while rewinding undoes, one frame.
+    fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        if self.rewinding {
+            self.rewind.rewind(&mut self.chip8);
+        } else {
+            self.rewind.push(&self.chip8);
+            if let Some(playback) = &mut self.playback {
+                playback.apply(&mut self.chip8);
+            }
+            if let Some(gdb) = &mut self.gdb {
+                if let Err(err) = gdb.run_frame(&mut self.chip8) {
+                    eprintln!("gdb connection failed: {}", err);
+                }
+            } else if let Some(debugger) = &mut self.debugger {
+                if let Some(stop) = debugger.run_frame(&mut self.chip8) {
+                    println!("{}", stop);
+                    print!("{}", debugger::location(&self.chip8));
+                    Frontend::prompt();
+                }
+            } else {
+                let result = self.chip8.step_frame();
+                self.check(result)?;
+            }
+        }
+        self.record_gif_frame();
+        // Rewinding runs the frame count backwards, which stays silent.
+        if let Some(audio) = &mut self.audio {
+            if let Err(err) = audio.update(&self.chip8) {
+                eprintln!("Audio output failed, sound is disabled: {}", err);
+                self.audio = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn title(&self) -> String {
+        format!("CHIP8 - {}", self.speed.describe(&self.chip8))
+    }
+
+    fn window_size(&self) -> [u32; 2] {
+        [Chip8::WIDTH as u32 * self.display.scale, Chip8::HEIGHT as u32 * self.display.scale]
+    }
+
+    fn check(&self, result: Result<(), Chip8Error>) -> Result<(), Chip8Error> {
+        if let Err(err) = result {
+            self.crash_report(&err);
+        }
+        result
+    }
+
+    fn crash_report(&self, err: &Chip8Error) {
+        eprintln!("CHIP8 crashed: {}", err);
+        for (x, value) in self.chip8.v().iter().enumerate() {
+            eprint!("V{:X}={:02x} ", x, value);
+        }
+        eprintln!();
+        eprintln!(
+            "I={:04x} PC={:04x} SP={:02x} DT={:02x} ST={:02x}",
+            self.chip8.i(),
+            self.chip8.pc(),
+            self.chip8.sp(),
+            self.chip8.delay(),
+            self.chip8.sound(),
+        );
+        eprintln!("stack: {:04x?}", &self.chip8.stack()[..usize::from(self.chip8.sp())]);
+    }
+
+    fn poll_console(&mut self) {
+        let (debugger, console) = match (&mut self.debugger, &self.console) {
+            (Some(debugger), Some(console)) => (debugger, console),
+            _ => return,
+        };
+        while let Ok(line) = console.try_recv() {
+            print!("{}", debugger.execute(&mut self.chip8, &line));
+            if debugger.is_running() {
+                self.next_frame = SystemTime::now();
+            }
+            Frontend::prompt();
+        }
+    }
+
+    fn prompt() {
+        print!("(chip8) ");
+        io::stdout().flush().ok();
+    }
+
+    // Starts tracing to `<rom>.trace` unless a tracer was set up already.
+    fn toggle_trace(&mut self) {
+        let enabled = match self.chip8.tracer_mut() {
+            Some(tracer) => {
+                let enabled = !tracer.is_enabled();
+                tracer.set_enabled(enabled);
+                enabled
+            },
+            None => {
+                let path = self.rom_path.with_extension("trace");
+                match fs::File::create(&path) {
+                    Ok(file) => {
+                        let output = Box::new(io::BufWriter::new(file));
+                        self.chip8.set_tracer(Some(Tracer::new(output, TraceFormat::Text)));
+                        println!("Tracing to {}", path.display());
+                        true
+                    },
+                    Err(err) => {
+                        eprintln!("Can't trace to {}: {}", path.display(), err);
+                        return;
+                    },
+                }
+            },
+        };
+        println!("Tracing {}", if enabled { "on" } else { "off" });
+    }
+
+    fn capture_path(&self, extension: &str) -> PathBuf {
+        self.rom_path.with_extension(format!("{}.{}", self.chip8.frames(), extension))
+    }
+
+    // Capture errors are reported but never stop the emulator.
+    fn screenshot(&self) {
+        let path = self.capture_path("png");
+        let result = fs::File::create(&path).and_then(|file| {
+            frame::write_png(&self.chip8, &self.display.palette, io::BufWriter::new(file), self.display.scale as usize)
+        });
+        match result {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(err) => eprintln!("Can't save screenshot to {}: {}", path.display(), err),
+        }
+    }
+
+    fn toggle_gif(&mut self) {
+        if let Some((path, recorder)) = self.gif.take() {
+            let frames = recorder.frames();
+            match recorder.finish().and_then(|mut output| output.flush()) {
+                Ok(()) => println!("Saved {} frames to {}", frames, path.display()),
+                Err(err) => eprintln!("Can't save GIF to {}: {}", path.display(), err),
+            }
+            return;
+        }
+        let path = self.capture_path("gif");
+        // Half the lo-res scale keeps the GIF the size of the window.
+        let scale = (self.display.scale as usize / 2).max(1);
+        let recorder = fs::File::create(&path).and_then(|file| {
+            GifRecorder::new(io::BufWriter::new(file), &self.display.palette, scale)
+        });
+        match recorder {
+            Ok(recorder) => {
+                println!("Recording GIF to {}", path.display());
+                self.gif = Some((path, recorder));
+            },
+            Err(err) => eprintln!("Can't record GIF to {}: {}", path.display(), err),
+        }
+    }
+
+    fn record_gif_frame(&mut self) {
+        let result = match &mut self.gif {
+            Some((_, recorder)) => recorder.add_frame(&self.chip8),
+            None => return,
+        };
+        if let Err(err) = result {
+            if let Some((path, _)) = self.gif.take() {
+                eprintln!("Can't record GIF to {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    fn is_movie_active(&self) -> bool {
+        self.recording.is_some() || self.playback.is_some()
+    }
+
+    fn slot_path(&self, slot: u32) -> PathBuf {
+        self.rom_path.with_extension(format!("state{}", slot))
+    }
+
+    // Slot errors are reported but never stop the emulator.
+    fn save_slot(&self, slot: u32) {
+        let path = self.slot_path(slot);
+        match fs::write(&path, self.chip8.save_state()) {
+            Ok(()) => println!("Saved slot {} to {}", slot, path.display()),
+            Err(err) => eprintln!("Can't save slot {} to {}: {}", slot, path.display(), err),
+        }
+    }
+
+    fn load_slot(&mut self, slot: u32) {
+        let path = self.slot_path(slot);
+        let result = fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| self.chip8.load_state(&data).map_err(|err| err.to_string()));
+        match result {
+            Ok(()) => {
+                println!("Loaded slot {} from {}", slot, path.display());
+                self.phosphor.clear();
+                self.next_frame = SystemTime::now();
+            },
+            Err(err) => eprintln!("Can't load slot {} from {}: {}", slot, path.display(), err),
+        }
+    }
+
+    fn pause(&mut self) {
+        self.pause = true;
+    }
+
+    fn unpause(&mut self) {
+        self.pause = false;
+        self.next_frame = SystemTime::now();
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.pause {
+            self.unpause();
+        } else {
+            self.pause();
+        }
+    }
+}
+
+fn color(rgb: [u8; 3]) -> [f32; 4] {
+    [f32::from(rgb[0]) / 255.0, f32::from(rgb[1]) / 255.0, f32::from(rgb[2]) / 255.0, 1.0]
+}
+
+// Keymap name of a key: piston's name in lowercase, with the digit keys
+// D0-D9 as plain digits.
+fn key_name(key: Key) -> String {
+    let name = format!("{:?}", key).to_lowercase();
+    match name.strip_prefix('d') {
+        Some(digit) if digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => digit.to_string(),
+        _ => name,
+    }
+}
+
+// The sound device, or silence if there is none.
+pub fn device_audio() -> Box<dyn AudioBackend> {
+    match rodio::default_output_device() {
+        Some(device) => {
+            let queue = Arc::new(Mutex::new(VecDeque::new()));
+            let sink = rodio::Sink::new(&device);
+            sink.append(QueueSource { queue: queue.clone(), buffer: VecDeque::new() });
+            Box::new(DeviceAudio { queue, _sink: sink })
+        },
+        None => {
+            eprintln!("No audio output device, sound is disabled");
+            Box::new(chip8_emu::NullAudio)
+        },
+    }
+}
+
+// Hands samples to rodio through a queue. Frames are generated as the
+// emulation loop runs them, the device drains them at its own pace.
+struct DeviceAudio {
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    _sink: rodio::Sink,
+}
+
+impl DeviceAudio {
+    // About 100 ms; older samples are dropped when the device falls behind,
+    // so the sound never lags the picture by more.
+    const MAX_QUEUED: usize = Audio::SAMPLE_RATE as usize / 10;
+}
+
+impl AudioBackend for DeviceAudio {
+    fn sample_rate(&self) -> u32 {
+        Audio::SAMPLE_RATE
+    }
+
+    fn play(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut queue = self.queue.lock().map_err(|_| io::Error::other("audio thread panicked"))?;
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(DeviceAudio::MAX_QUEUED);
+        queue.drain(..excess);
+        Ok(())
+    }
+}
+
+// Plays queued samples, and silence while the queue is empty, e.g. when
+// paused.
+struct QueueSource {
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    // Taken from the queue a chunk at a time to keep locking rare.
+    buffer: VecDeque<f32>,
+}
+
+impl QueueSource {
+    const CHUNK: usize = 256;
+}
+
+impl Iterator for QueueSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.buffer.is_empty() {
+            if let Ok(mut queue) = self.queue.lock() {
+                let count = queue.len().min(QueueSource::CHUNK);
+                self.buffer.extend(queue.drain(..count));
+            }
+            if self.buffer.is_empty() {
+                self.buffer.resize(QueueSource::CHUNK, 0.0);
+            }
+        }
+        self.buffer.pop_front()
+    }
+}
+
+impl rodio::Source for QueueSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        Audio::SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
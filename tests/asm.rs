@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::Path;
+
+use chip8_emu::asm::assemble;
+use chip8_emu::disasm::disassemble;
+
+fn error(source: &str) -> String {
+    assemble(source).unwrap_err().to_string()
+}
+
+#[test]
+fn assembles() {
+    let source = "start:\n    LD V0, size\n    CALL sub\n    JP start\nsize EQU 2 + 1\nsub:\n    RET\n    db \"#...#...\", 0x0f\n    dw start\n";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x60, 0x03, 0x22, 0x06, 0x12, 0x00, 0x00, 0xee, 0x88, 0x0f, 0x02, 0x00]
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(error("    FOO V0"), "1:5: unknown mnemonic 'FOO'");
+    assert_eq!(error("\n    LD V0, V1, V2"), "2:5: invalid operands for LD");
+    assert_eq!(error("    JP nowhere"), "1:8: undefined symbol 'nowhere'");
+    assert_eq!(error("a:\na:"), "2:1: 'a' is already defined");
+    assert_eq!(error("v0:"), "1:1: 'v0' is a reserved name");
+    assert_eq!(error("    LD V0, 0x100"), "1:12: value 0x100 out of range");
+    assert_eq!(error("    LD V0, 0x7fffffffffffffff + 1"), "1:12: expression overflows");
+    assert_eq!(error("    LD V0, \"abc"), "1:12: unterminated string");
+    assert_eq!(error("    db \"#..o\""), "1:12: invalid sprite pixel 'o'");
+    assert_eq!(error("one EQU two\ntwo EQU one\n    LD V0, one"), "1:9: constant 'two' is recursive");
+}
+
+#[test]
+fn program_too_large() {
+    // Everything from 0x200 to the top of the XO-CHIP address space fits.
+    let fits = "    db 0\n".repeat(0x10000 - 0x200);
+    assert_eq!(assemble(&fits).unwrap().len(), 0x10000 - 0x200);
+    let source = format!("{}    db 0\n", fits);
+    assert_eq!(error(&source), format!("{}:5: program does not fit in memory", 0x10000 - 0x200 + 1));
+}
+
+#[test]
+fn disassembly_round_trips() {
+    for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = fs::read(&path).unwrap();
+        let listing = disassemble(&rom).to_string();
+        assert_eq!(assemble(&listing).unwrap(), rom, "{}", path.display());
+    }
+}
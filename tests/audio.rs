@@ -0,0 +1,59 @@
+use chip8_emu::{Beeper, Chip8, Tone};
+
+const SAMPLE_RATE: u32 = 44100;
+const FRAME: usize = SAMPLE_RATE as usize / 60;
+
+// Sets ST to `sound` halfway through the first frame, then loops.
+fn beep_rom(sound: u8) -> Chip8 {
+    Chip8::new(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, sound, 0xf0, 0x18, 0x12, 0x0a])
+}
+
+fn beeper() -> Beeper {
+    Beeper::new(Tone { volume: 1.0, ..Tone::default() }, SAMPLE_RATE)
+}
+
+// The samples of the first `frames` frames of `beep_rom(sound)`.
+fn beep(sound: u8, frames: usize) -> Vec<f32> {
+    let mut chip8 = beep_rom(sound);
+    let mut beeper = beeper();
+    let mut samples = Vec::new();
+    for _ in 0..frames {
+        chip8.step_frame().unwrap();
+        beeper.generate(&chip8, &mut samples);
+    }
+    beeper.flush(&mut samples);
+    assert_eq!(samples.len(), frames * FRAME);
+    samples
+}
+
+#[test]
+fn one_frame_beep() {
+    let samples = beep(1, 4);
+    assert!(samples[..FRAME].iter().all(|&sample| sample != 0.0));
+    assert!(samples[FRAME..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn beep_lasts_the_sound_timer() {
+    let samples = beep(30, 40);
+    assert!(samples[..30 * FRAME].iter().all(|&sample| sample != 0.0));
+    assert!(samples[30 * FRAME..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn states_keep_the_beep() {
+    // ST has already ticked back to 0, but the frame still beeps.
+    let mut chip8 = beep_rom(1);
+    chip8.step_frame().unwrap();
+    assert_eq!(chip8.sound(), 0);
+    assert!(chip8.sounded());
+    let mut restored = Chip8::new(&[]);
+    restored.load_state(&chip8.save_state()).unwrap();
+    assert!(restored.sounded());
+    let mut beeper = beeper();
+    let mut samples = Vec::new();
+    beeper.generate(&restored, &mut samples);
+    beeper.flush(&mut samples);
+    assert_eq!(samples.len(), FRAME);
+    assert!(samples.iter().all(|&sample| sample != 0.0));
+}
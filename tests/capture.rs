@@ -0,0 +1,49 @@
+use chip8_emu::{Chip8, GifRecorder, Palette};
+
+// Decodes every frame of a GIF into rows of palette indices.
+fn frames(data: &[u8]) -> (usize, usize, Vec<Vec<u8>>) {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(data).unwrap();
+    let (width, height) = (usize::from(decoder.width()), usize::from(decoder.height()));
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push(frame.buffer.to_vec());
+    }
+    (width, height, frames)
+}
+
+#[test]
+fn hi_res_keeps_every_pixel() {
+    // HIGH, then a single pixel at (1, 1), which a lo-res canvas would drop.
+    let rom = [0x00, 0xff, 0xa2, 0x0a, 0x60, 0x01, 0xd0, 0x01, 0x12, 0x08, 0x80];
+    let mut chip8 = Chip8::new(&rom);
+    let mut recorder = GifRecorder::new(Vec::new(), &Palette::CLASSIC, 1).unwrap();
+    for _ in 0..2 {
+        chip8.step_frame().unwrap();
+        recorder.add_frame(&chip8).unwrap();
+    }
+    let (width, height, frames) = frames(&recorder.finish().unwrap());
+    assert_eq!((width, height), (128, 64));
+    let lit: Vec<usize> = frames.last().unwrap().iter().enumerate()
+        .filter(|&(_, &pixel)| pixel != 0)
+        .map(|(index, _)| index)
+        .collect();
+    assert_eq!(lit, [width + 1]);
+}
+
+#[test]
+fn lo_res_is_doubled() {
+    // A single pixel at (1, 1) covers 2x2 pixels of the canvas.
+    let rom = [0xa2, 0x08, 0x60, 0x01, 0xd0, 0x01, 0x12, 0x06, 0x80];
+    let mut chip8 = Chip8::new(&rom);
+    let mut recorder = GifRecorder::new(Vec::new(), &Palette::CLASSIC, 1).unwrap();
+    chip8.step_frame().unwrap();
+    recorder.add_frame(&chip8).unwrap();
+    let (width, _, frames) = frames(&recorder.finish().unwrap());
+    let lit: Vec<usize> = frames[0].iter().enumerate()
+        .filter(|&(_, &pixel)| pixel != 0)
+        .map(|(index, _)| index)
+        .collect();
+    assert_eq!(lit, [2 * width + 2, 2 * width + 3, 3 * width + 2, 3 * width + 3]);
+}
@@ -0,0 +1,183 @@
+// Runs test ROMs headlessly and compares the final display against the
+// golden framebuffers in tests/golden. Run with UPDATE_GOLDEN=1 to rewrite
+// them after an intended change, then review the diff.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8_emu::asm::assemble;
+use chip8_emu::{frame, Chip8, Quirks};
+
+const SEED: u64 = 0x5eed;
+
+// Presses `key` at `frame` and releases it `frames` later.
+struct Press {
+    frame: u64,
+    key: u8,
+    frames: u64,
+}
+
+fn path(relative: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(relative)
+}
+
+fn asset(name: &str) -> Vec<u8> {
+    fs::read(path(&format!("assets/{}", name))).unwrap()
+}
+
+fn assembled(name: &str) -> Vec<u8> {
+    let source = fs::read_to_string(path(&format!("tests/roms/{}", name))).unwrap();
+    assemble(&source).unwrap_or_else(|err| panic!("{}:{}", name, err))
+}
+
+fn run(rom: &[u8], quirks: Quirks, frames: u64, presses: &[Press]) -> Chip8 {
+    let mut chip8 = Chip8::with_seed(rom, SEED);
+    chip8.set_quirks(quirks);
+    for frame in 0..frames {
+        for press in presses {
+            if press.frame == frame {
+                chip8.set_key(press.key, true);
+            }
+            if press.frame + press.frames == frame {
+                chip8.set_key(press.key, false);
+            }
+        }
+        chip8.step_frame().unwrap();
+    }
+    chip8
+}
+
+fn assert_golden(name: &str, chip8: &Chip8) {
+    let golden_path = path(&format!("tests/golden/{}.txt", name));
+    let actual = frame::ascii(chip8);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&golden_path)
+        .unwrap_or_else(|err| panic!("{}: {}, run with UPDATE_GOLDEN=1 to create it", golden_path.display(), err));
+    assert!(actual == expected, "{} differs from {}:\n{}", name, golden_path.display(), actual);
+}
+
+#[test]
+fn opcodes() {
+    let chip8 = run(&assembled("opcodes.asm"), Quirks::MODERN, 60, &[]);
+    assert_golden("opcodes", &chip8);
+}
+
+#[test]
+fn quirks_cosmac_vip() {
+    let chip8 = run(&assembled("quirks.asm"), Quirks::COSMAC_VIP, 60, &[]);
+    assert_golden("quirks_vip", &chip8);
+}
+
+#[test]
+fn quirks_chip48() {
+    let chip8 = run(&assembled("quirks.asm"), Quirks::CHIP48, 60, &[]);
+    assert_golden("quirks_chip48", &chip8);
+}
+
+#[test]
+fn quirks_schip() {
+    let chip8 = run(&assembled("quirks.asm"), Quirks::SCHIP, 60, &[]);
+    assert_golden("quirks_schip", &chip8);
+}
+
+#[test]
+fn quirks_xo_chip() {
+    let chip8 = run(&assembled("quirks.asm"), Quirks::XO_CHIP, 60, &[]);
+    assert_golden("quirks_xochip", &chip8);
+}
+
+#[test]
+fn quirks_modern() {
+    let chip8 = run(&assembled("quirks.asm"), Quirks::MODERN, 60, &[]);
+    assert_golden("quirks_modern", &chip8);
+}
+
+#[test]
+fn super_chip_on_vip() {
+    let chip8 = run(&assembled("vip_mode.asm"), Quirks::COSMAC_VIP, 60, &[]);
+    assert!(!chip8.is_halted() && !chip8.is_hires());
+    assert_golden("vip_mode", &chip8);
+    // Fx30, Fx75 and Fx85 don't exist at all.
+    for opcode in [[0xf0, 0x30], [0xf0, 0x75], [0xf0, 0x85]] {
+        let mut chip8 = Chip8::with_seed(&opcode, SEED);
+        chip8.set_quirks(Quirks::COSMAC_VIP);
+        assert!(chip8.step().is_err(), "{:02x}{:02x} ran on the VIP", opcode[0], opcode[1]);
+    }
+}
+
+// PC, return addresses and I wrap around at the top of 64 KiB XO-CHIP
+// memory.
+#[test]
+fn top_of_memory() {
+    let machine = |address: u16, code: &[u8]| {
+        let mut chip8 = Chip8::with_seed(&[0x12, 0x00], SEED);
+        chip8.set_xo_chip(true);
+        chip8.set_quirks(Quirks::XO_CHIP);
+        let start = usize::from(address);
+        chip8.memory_mut()[start..start + code.len()].copy_from_slice(code);
+        chip8.set_pc(address);
+        chip8
+    };
+
+    let mut chip8 = machine(0xfffe, &[0x60, 0x01]);
+    chip8.step().unwrap();
+    assert_eq!(chip8.pc(), 0x0000);
+
+    let mut chip8 = machine(0xfffc, &[0x30, 0x00]);
+    chip8.step().unwrap();
+    assert_eq!(chip8.pc(), 0x0000);
+
+    let mut chip8 = machine(0xfffc, &[0xf0, 0x00, 0x12, 0x34]);
+    chip8.step().unwrap();
+    assert_eq!((chip8.pc(), chip8.i()), (0x0000, 0x1234));
+
+    let mut chip8 = machine(0xfffe, &[0x22, 0x00]);
+    chip8.memory_mut()[0x200..0x202].copy_from_slice(&[0x00, 0xee]);
+    chip8.step().unwrap();
+    chip8.step().unwrap();
+    assert_eq!(chip8.pc(), 0x0000);
+
+    for opcode in [0x55, 0x65] {
+        let mut chip8 = machine(0x300, &[0xff, opcode]);
+        chip8.set_i(0xfff0);
+        chip8.step().unwrap();
+        assert_eq!((chip8.pc(), chip8.i()), (0x302, 0x0000));
+    }
+}
+
+#[test]
+fn sqrt_test() {
+    let chip8 = run(&asset("SQRT Test [Sergey Naydenov, 2010].ch8"), Quirks::default(), 300, &[]);
+    assert_golden("sqrt_test", &chip8);
+}
+
+#[test]
+fn delay_timer_test() {
+    // Forty presses of 2 and one of 8 leave the counter at 39. The countdown
+    // started by 5 redraws every couple of frames and would be caught
+    // half drawn, so the test stops while the ROM waits for the next key.
+    let mut presses: Vec<Press> = (0..40).map(|n| Press { frame: 2 + n * 4, key: 0x2, frames: 1 }).collect();
+    presses.push(Press { frame: 170, key: 0x8, frames: 1 });
+    let chip8 = run(&asset("Delay Timer Test [Matthew Mikolay, 2010].ch8"), Quirks::default(), 178, &presses);
+    assert!(chip8.is_waiting());
+    assert_golden("delay_timer_test", &chip8);
+}
+
+#[test]
+fn random_number_test() {
+    let presses = [Press { frame: 30, key: 0x0, frames: 5 }, Press { frame: 60, key: 0x0, frames: 5 }];
+    let chip8 = run(&asset("Random Number Test [Matthew Mikolay, 2010].ch8"), Quirks::default(), 90, &presses);
+    assert_golden("random_number_test", &chip8);
+}
+
+#[test]
+fn keypad_test() {
+    // The pressed key stays highlighted for 16 frames.
+    let presses = [Press { frame: 40, key: 0xa, frames: 2 }];
+    let chip8 = run(&asset("Keypad Test [Hap, 2006].ch8"), Quirks::default(), 50, &presses);
+    assert_golden("keypad_test", &chip8);
+}
@@ -0,0 +1,85 @@
+use chip8_emu::debugger::{Access, Stop};
+use chip8_emu::{Chip8, Debugger};
+
+// 200: LD V0, 5; CALL 208; ADD V0, 1; JP 206
+// 208: LD I, 20e; LD [I], V0; RET; 20e: db 0
+const ROM: [u8; 15] = [
+    0x60, 0x05, 0x22, 0x08, 0x70, 0x01, 0x12, 0x06, 0xa2, 0x0e, 0xf0, 0x55, 0x00, 0xee, 0x00,
+];
+
+// Runs frames until the debugger stops the machine.
+fn run(debugger: &mut Debugger, chip8: &mut Chip8) -> Option<Stop> {
+    (0..10).find_map(|_| debugger.run_frame(chip8))
+}
+
+#[test]
+fn breakpoints() {
+    let mut chip8 = Chip8::new(&ROM);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip8, "break 204"), "1: break 0204\n");
+    assert_eq!(debugger.execute(&mut chip8, "break 206 if V0 == 07"), "2: break 0206 if V0 == 07\n");
+    debugger.execute(&mut chip8, "continue");
+    assert_eq!(run(&mut debugger, &mut chip8), Some(Stop::Breakpoint { id: 1 }));
+    assert_eq!(chip8.pc(), 0x204);
+    // V0 is 6 at 206, so the condition never holds.
+    debugger.execute(&mut chip8, "continue");
+    assert_eq!(run(&mut debugger, &mut chip8), None);
+    assert_eq!(debugger.execute(&mut chip8, "delete 1"), "deleted 1: break 0204\n");
+    assert_eq!(debugger.execute(&mut chip8, "info"), "2: break 0206 if V0 == 07\n");
+}
+
+#[test]
+fn watchpoints() {
+    let mut chip8 = Chip8::new(&ROM);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip8, "rwatch 20e");
+    debugger.execute(&mut chip8, "watch 20d 2");
+    debugger.execute(&mut chip8, "continue");
+    let stop = Stop::Watchpoint { id: 2, address: 0x20e, access: Access::Write };
+    assert_eq!(run(&mut debugger, &mut chip8), Some(stop));
+    assert_eq!(chip8.pc(), 0x20a);
+}
+
+#[test]
+fn stepping() {
+    let mut chip8 = Chip8::new(&ROM);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip8, "step"), "0202: CALL 0x208\n");
+    // Over the call, which runs until it returns.
+    assert_eq!(debugger.execute(&mut chip8, "next"), "");
+    assert_eq!(run(&mut debugger, &mut chip8), Some(Stop::Step));
+    assert_eq!((chip8.pc(), chip8.v()[0]), (0x204, 5));
+
+    let mut chip8 = Chip8::new(&ROM);
+    assert_eq!(debugger.execute(&mut chip8, "step 3"), "020a: LD [I], V0\n");
+    assert_eq!(debugger.execute(&mut chip8, "stack"), "#0 0202\n");
+    assert_eq!(debugger.execute(&mut chip8, "finish"), "");
+    assert_eq!(run(&mut debugger, &mut chip8), Some(Stop::Step));
+    assert_eq!(chip8.pc(), 0x204);
+    assert_eq!(debugger.execute(&mut chip8, "finish"), "error: not in a subroutine\n");
+}
+
+#[test]
+fn step_over_at_the_top_of_memory() {
+    let mut chip8 = Chip8::new(&[0x00, 0xee]);
+    chip8.set_xo_chip(true);
+    chip8.memory_mut()[0xfffe..].copy_from_slice(&[0x22, 0x00]);
+    chip8.set_pc(0xfffe);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip8, "next"), "");
+    assert_eq!(run(&mut debugger, &mut chip8), Some(Stop::Step));
+    assert_eq!(chip8.pc(), 0x0000);
+}
+
+#[test]
+fn memory_and_errors() {
+    let mut chip8 = Chip8::new(&ROM);
+    let mut debugger = Debugger::new();
+    assert_eq!(debugger.execute(&mut chip8, "x 200 4"), "0200: 60 05 22 08\n");
+    assert_eq!(debugger.execute(&mut chip8, "x ffc 18446744073709551615"), "0ffc: 00 00 00 00\n");
+    assert_eq!(debugger.execute(&mut chip8, "break 1ffff"), "error: hex value \"1ffff\" is above ffff\n");
+    assert_eq!(debugger.execute(&mut chip8, "break"), "error: missing address\n");
+    assert_eq!(debugger.execute(&mut chip8, "break 200 if V0 ~ 1"), "error: unknown operator \"~\"\n");
+    assert_eq!(debugger.execute(&mut chip8, "delete 9"), "error: no breakpoint or watchpoint 9\n");
+    assert_eq!(debugger.execute(&mut chip8, "frobnicate"), "error: unknown command \"frobnicate\", try help\n");
+}
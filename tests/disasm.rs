@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+
+use chip8_emu::disasm::{disassemble, LabelKind};
+
+fn asset(name: &str) -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(name)).unwrap()
+}
+
+// The bytes in the trailing comments of a listing, in order.
+fn listed_bytes(listing: &str) -> Vec<u8> {
+    listing.lines()
+        .filter_map(|line| line.split_once("; "))
+        .flat_map(|(_, comment)| {
+            let hex = comment.split_whitespace().nth(1).unwrap();
+            (0..hex.len()).step_by(2).map(move |i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn listing_covers_every_byte() {
+    for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")).unwrap() {
+        let rom = fs::read(entry.unwrap().path()).unwrap();
+        assert_eq!(listed_bytes(&disassemble(&rom).to_string()), rom);
+    }
+}
+
+#[test]
+fn labels() {
+    // start: CALL sub; JP start; sub: LD I, data; RET; data: db 0xf0
+    let rom = [0x22, 0x04, 0x12, 0x00, 0xa2, 0x08, 0x00, 0xee, 0xf0];
+    let disassembly = disassemble(&rom);
+    let labels: Vec<(u16, LabelKind)> = disassembly.labels().iter().map(|(&addr, &kind)| (addr, kind)).collect();
+    assert_eq!(labels, [(0x200, LabelKind::Start), (0x204, LabelKind::Subroutine), (0x208, LabelKind::Data)]);
+    assert!(disassembly.is_code(0x206));
+    assert!(!disassembly.is_code(0x208));
+    let listing = disassembly.to_string();
+    assert!(listing.contains("CALL sub_204"), "{}", listing);
+    assert!(listing.contains("JP start"), "{}", listing);
+    assert!(listing.contains("LD I, data_208"), "{}", listing);
+}
+
+#[test]
+fn unreachable_bytes_are_data() {
+    let rom = asset("Breakout [Carmelo Cortez, 1979].ch8");
+    let disassembly = disassemble(&rom);
+    assert!(disassembly.is_code(0x200));
+    assert!((0x200..0x200 + rom.len() as u16).any(|addr| !disassembly.is_code(addr)));
+}
+
+#[test]
+fn full_size_xo_chip_rom() {
+    // 64 KiB of memory leaves 0xfe00 bytes above 0x200, execution runs off
+    // the top of the address space.
+    let rom: Vec<u8> = [0x60, 0x01].iter().copied().cycle().take(0xfe00).collect();
+    let listing = disassemble(&rom).to_string();
+    assert!(listing.trim_end().ends_with("; fffe: 6001"), "{}", &listing[listing.len() - 200..]);
+    assert_eq!(listed_bytes(&listing), rom);
+    // Bytes past the top of memory can't be loaded or listed.
+    let mut rom = rom;
+    rom.extend_from_slice(&[0x12, 0x00]);
+    assert_eq!(listed_bytes(&disassemble(&rom).to_string()).len(), 0xfe00);
+}
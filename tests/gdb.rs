@@ -0,0 +1,121 @@
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chip8_emu::{Chip8, GdbStub};
+
+// 200: LD V0, 5; LD I, 20e; LD [I], V0; JP 206
+const ROM: [u8; 8] = [0x60, 0x05, 0xa2, 0x0e, 0xf0, 0x55, 0x12, 0x06];
+
+// A gdb client talking to a stub served from the test thread.
+struct Session {
+    stub: GdbStub,
+    chip8: Chip8,
+    client: TcpStream,
+}
+
+impl Session {
+    fn new() -> Session {
+        let mut stub = GdbStub::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(stub.local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let mut chip8 = Chip8::new(&ROM);
+        while !stub.is_connected() {
+            stub.poll(&mut chip8).unwrap();
+        }
+        Session { stub, chip8, client }
+    }
+
+    // Sends raw bytes and returns everything the stub answers.
+    fn exchange(&mut self, data: &str) -> String {
+        self.client.write_all(data.as_bytes()).unwrap();
+        let mut reply = Vec::new();
+        let mut buffer = [0; 4096];
+        // Until an ack or nack and, after an ack, a whole reply packet.
+        let complete = |reply: &[u8]| match reply.first() {
+            Some(b'-') => true,
+            Some(b'+') => reply.iter().position(|&byte| byte == b'#').is_some_and(|end| end + 3 == reply.len()),
+            _ => false,
+        };
+        for _ in 0..100 {
+            self.stub.poll(&mut self.chip8).unwrap();
+            self.stub.run_frame(&mut self.chip8).unwrap();
+            if let Ok(n) = self.client.read(&mut buffer) {
+                reply.extend_from_slice(&buffer[..n]);
+            }
+            if complete(&reply) {
+                break;
+            }
+        }
+        String::from_utf8(reply).unwrap()
+    }
+
+    // Sends a packet and returns the reply packet's data.
+    fn packet(&mut self, data: &str) -> String {
+        let reply = self.exchange(&frame(data));
+        let reply = reply.strip_prefix('+').unwrap_or_else(|| panic!("{:?} not acknowledged: {:?}", data, reply));
+        let (body, checksum) = reply.strip_prefix('$').and_then(|reply| reply.split_once('#')).unwrap();
+        assert_eq!(frame(body), format!("${}#{}", body, checksum));
+        body.to_string()
+    }
+}
+
+fn frame(data: &str) -> String {
+    let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    format!("${}#{:02x}", data, checksum)
+}
+
+#[test]
+fn checksums() {
+    let mut session = Session::new();
+    assert_eq!(session.exchange("$?#3f"), "+$S05#b8");
+    // A bad checksum is refused and gdb sends the packet again.
+    assert_eq!(session.exchange("$?#00"), "-");
+    assert_eq!(session.exchange("+$?#3f"), "+$S05#b8");
+}
+
+#[test]
+fn registers() {
+    let mut session = Session::new();
+    assert_eq!(session.packet("P0=2a"), "OK");
+    assert_eq!(session.packet("p0"), "2a");
+    assert_eq!(session.packet("p11"), "0200");
+    assert_eq!(session.packet("p15"), "E00");
+}
+
+#[test]
+fn memory_bounds() {
+    let mut session = Session::new();
+    assert_eq!(session.packet("m200,4"), "6005a20e");
+    assert_eq!(session.packet("mffe,2"), "0000");
+    assert_eq!(session.packet("mfff,2"), "E01");
+    assert_eq!(session.packet("mffffffffffffffff,2"), "E01");
+    assert_eq!(session.packet("M20e,2:1234"), "OK");
+    assert_eq!(session.packet("m20e,2"), "1234");
+    assert_eq!(session.packet("Mfff,2:1234"), "E01");
+    assert_eq!(session.packet("Mffffffffffffffff,2:1234"), "E01");
+    assert_eq!(session.packet("M200,2:12"), "E01");
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let mut session = Session::new();
+    assert_eq!(session.packet("Z0,ffffffffffffffff,2"), "E01");
+    assert_eq!(session.packet("Z2,ffff,2"), "E01");
+    assert_eq!(session.packet("Z2,20e,1"), "OK");
+    assert_eq!(session.packet("c"), "T05watch:20e;");
+    assert_eq!(session.packet("p11"), "0204");
+    assert_eq!(session.packet("z2,20e,1"), "OK");
+    assert_eq!(session.packet("Z0,206,2"), "OK");
+    assert_eq!(session.packet("c"), "S05");
+    assert_eq!(session.packet("p11"), "0206");
+    assert_eq!(session.packet("s"), "S05");
+}
+
+#[test]
+fn target_description() {
+    let mut session = Session::new();
+    let reply = session.packet("qXfer:features:read:target.xml:0,ffffffffffffffff");
+    assert!(reply.starts_with("l<?xml"), "{}", reply);
+    assert_eq!(session.packet("qXfer:features:read:target.xml:ffffffffffffffff,10"), "l");
+}
@@ -0,0 +1,27 @@
+use chip8_emu::{Action, Config};
+
+#[test]
+fn scancodes_replace_key_names() {
+    let config = Config::parse("scancodes = true\n[keypad]\n5 = 17\n[hotkeys]\nquit = 1\n").unwrap();
+    let keymap = config.keymap("game.ch8", &[]);
+    assert!(keymap.uses_scancodes());
+    assert_eq!(keymap.action("17"), Some(Action::Keypad(0x5)));
+    assert_eq!(keymap.action("1"), Some(Action::Quit));
+    // None of the key name defaults are left.
+    assert_eq!(keymap.action("w"), None);
+    assert_eq!(keymap.action("escape"), None);
+    assert_eq!(keymap.keys(Action::Keypad(0x4)), Vec::<&str>::new());
+}
+
+#[test]
+fn scancodes_need_quit() {
+    let err = Config::parse("scancodes = true\n[keypad]\n5 = 17\n").unwrap_err();
+    assert_eq!(err.to_string(), "scancodes: binding by scancode needs a scancode for hotkeys.quit");
+    let err = Config::parse("[rom.\"game.ch8\"]\nscancodes = true\n").unwrap_err();
+    assert_eq!(err.to_string(), "rom.\"game.ch8\".scancodes: binding by scancode needs a scancode for hotkeys.quit");
+    // A ROM can go back to key names, and gets the default layout.
+    let config = Config::parse("scancodes = true\n[hotkeys]\nquit = 1\n[rom.\"game.ch8\"]\nscancodes = false\n").unwrap();
+    let keymap = config.keymap("game.ch8", &[]);
+    assert_eq!(keymap.action("escape"), Some(Action::Quit));
+    assert_eq!(keymap.action("1"), Some(Action::Keypad(0x1)));
+}
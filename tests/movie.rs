@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+use chip8_emu::movie::{rom_hash, KeyEvent};
+use chip8_emu::{Chip8, Movie, MovieError, Playback, Quirks};
+
+fn breakout() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("Breakout [Carmelo Cortez, 1979].ch8")).unwrap()
+}
+
+fn parse_error(text: &str) -> MovieError {
+    Movie::parse(text).unwrap_err()
+}
+
+#[test]
+fn text_round_trip() {
+    let movie = Movie {
+        rom_hash: 0x5d3e0f8a7c2b1e44,
+        seed: 42,
+        quirks: Quirks::SCHIP,
+        xo_chip: true,
+        instructions_per_second: 1000,
+        events: vec![
+            KeyEvent { frame: 120, key: 0x5, pressed: true },
+            KeyEvent { frame: 126, key: 0xa, pressed: false },
+        ],
+    };
+    let text = movie.to_string();
+    assert!(text.starts_with("chip8-movie 2\nrom 5d3e0f8a7c2b1e44\nseed 42\n"), "{}", text);
+    assert!(text.ends_with("xo 1\nips 1000\n120 5 down\n126 a up\n"), "{}", text);
+    assert_eq!(Movie::parse(&text).unwrap(), movie);
+}
+
+#[test]
+fn parse_errors() {
+    let header = "chip8-movie 2\nrom 00000000000000ff\nseed 1\nquirks 00\nxo 0\nips 540\n";
+    assert!(Movie::parse(header).is_ok());
+    let parse = |line, message: &str| MovieError::Parse { line, message: message.to_string() };
+    assert_eq!(parse_error(""), parse(1, "empty movie"));
+    assert_eq!(parse_error("chip8-movie 1\n"), parse(1, "not a chip8-movie 2 file"));
+    assert_eq!(parse_error("chip8-movie 2\nrom 00\nseed x\n"), parse(3, "bad seed"));
+    assert_eq!(parse_error("chip8-movie 2\nrom 00\nseed 1\n"), parse(3, "missing quirks"));
+    assert_eq!(parse_error("chip8-movie 2\nseed 1\n"), parse(2, "expected rom"));
+    assert_eq!(parse_error(&header.replace("ips 540", "ips 0")), parse(6, "bad ips"));
+    assert_eq!(parse_error(&header.replace("xo 0", "xo 2")), parse(5, "bad xo flag"));
+    assert_eq!(parse_error(&format!("{}1 10 down\n", header)), parse(7, "bad key"));
+    assert_eq!(parse_error(&format!("{}1 1 held\n", header)), parse(7, "expected down or up"));
+    assert_eq!(parse_error(&format!("{}1 1\n", header)), parse(7, "expected <frame> <key> <down|up>"));
+    assert_eq!(parse_error(&format!("{}5 1 down\n4 1 up\n", header)), parse(8, "events out of order"));
+}
+
+#[test]
+fn playback_is_deterministic() {
+    let rom = breakout();
+    let mut chip8 = Chip8::with_seed(&rom, 7);
+    chip8.set_quirks(Quirks::CHIP48);
+    chip8.set_instructions_per_second(700);
+    let mut movie = Movie::new(&rom, &chip8).unwrap();
+    for frame in 0..600 {
+        if frame % 40 == 10 {
+            let key = if frame % 80 == 10 { 0x4 } else { 0x6 };
+            chip8.set_key(key, true);
+            movie.record(&chip8, key, true);
+        }
+        if frame % 40 == 30 {
+            for key in [0x4, 0x6] {
+                chip8.set_key(key, false);
+                movie.record(&chip8, key, false);
+            }
+        }
+        chip8.step_frame().unwrap();
+    }
+
+    let movie = Movie::parse(&movie.to_string()).unwrap();
+    let mut replay = movie.start(&rom).unwrap();
+    let mut playback = Playback::new(&movie);
+    while replay.frames() < chip8.frames() {
+        playback.apply(&mut replay);
+        replay.step_frame().unwrap();
+    }
+    assert!(playback.is_finished());
+    assert_eq!(replay.display(), chip8.display());
+    assert!(replay.save_state() == chip8.save_state());
+}
+
+#[test]
+fn start_checks_the_rom() {
+    let rom = breakout();
+    let movie = Movie::new(&rom, &Chip8::with_seed(&rom, 1)).unwrap();
+    let err = movie.start(&[0x12, 0x00]).err().unwrap();
+    assert_eq!(err, MovieError::RomMismatch { expected: rom_hash(&rom), found: rom_hash(&[0x12, 0x00]) });
+    assert_eq!(Movie::new(&rom, &Chip8::with_rng(&rom, Box::new(rand::rngs::OsRng))).unwrap_err(), MovieError::NoSeed);
+}
@@ -0,0 +1,68 @@
+use chip8_emu::{Chip8, Rewind};
+
+// Counts frames in V0: ADD V0, 1; wait for the delay timer; loop.
+fn counter() -> Chip8 {
+    Chip8::new(&[0x70, 0x01, 0x61, 0x01, 0xf1, 0x15, 0xf1, 0x07, 0x31, 0x00, 0x12, 0x06, 0x12, 0x00])
+}
+
+#[test]
+fn rewinds_frame_by_frame() {
+    let mut chip8 = counter();
+    let mut rewind = Rewind::new(10);
+    let mut states = Vec::new();
+    for _ in 0..5 {
+        rewind.push(&chip8);
+        states.push(chip8.save_state());
+        chip8.step_frame().unwrap();
+    }
+    assert_eq!(rewind.frames(), 5);
+    while let Some(state) = states.pop() {
+        assert!(rewind.rewind(&mut chip8));
+        assert!(chip8.save_state() == state);
+    }
+    assert_eq!(rewind.frames(), 0);
+    assert!(!rewind.rewind(&mut chip8));
+}
+
+#[test]
+fn ring_drops_the_oldest_frames() {
+    let mut chip8 = counter();
+    let mut rewind = Rewind::new(3);
+    let mut states = Vec::new();
+    for _ in 0..8 {
+        rewind.push(&chip8);
+        states.push(chip8.save_state());
+        chip8.step_frame().unwrap();
+    }
+    assert_eq!(rewind.frames(), 3);
+    assert_eq!(rewind.depth(), 3);
+    // Only the last three frames are left, newest first.
+    for state in states[5..].iter().rev() {
+        assert!(rewind.rewind(&mut chip8));
+        assert!(chip8.save_state() == *state);
+    }
+    assert!(!rewind.rewind(&mut chip8));
+    assert!(chip8.save_state() == states[5]);
+
+    // Pushing after a rewind carries on from the restored frame.
+    rewind.push(&chip8);
+    chip8.step_frame().unwrap();
+    assert!(rewind.rewind(&mut chip8));
+    assert!(chip8.save_state() == states[5]);
+}
+
+#[test]
+fn zero_depth_and_clear() {
+    let mut chip8 = counter();
+    let mut rewind = Rewind::new(0);
+    rewind.push(&chip8);
+    assert_eq!(rewind.frames(), 0);
+    assert!(!rewind.rewind(&mut chip8));
+
+    let mut rewind = Rewind::new(4);
+    rewind.push(&chip8);
+    rewind.push(&chip8);
+    rewind.clear();
+    assert_eq!(rewind.frames(), 0);
+    assert!(!rewind.rewind(&mut chip8));
+}
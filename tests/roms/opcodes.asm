@@ -0,0 +1,337 @@
+; Opcode and flag conformance test.
+;
+; Every test draws a tick when all of its checks pass and a cross when any
+; of them fails, eight results per row. A check is a skip over `LD VC, 1`,
+; so VC is set whenever a skip that should happen doesn't.
+;
+;  1 CLS, DRW and collisions     10 VF as the destination of 8xy4-8xyE
+;  2 3xkk 4xkk 5xy0 9xy0         11 Annn Fx1E Fx55 Fx65
+;  3 6xkk 7xkk 8xy0              12 Fx33
+;  4 8xy1 8xy2 8xy3              13 2nnn 00EE
+;  5 8xy4                        14 Bnnn
+;  6 8xy5                        15 Fx15 Fx07
+;  7 8xy6                        16 Fx29
+;  8 8xy7                        17 Cxkk
+;  9 8xyE                        18 Ex9E ExA1
+
+start:
+    LD VC, 0
+    LD VD, 0
+    LD VE, 0
+
+; 1
+    LD I, ok
+    LD V0, 0
+    LD V1, 20
+    DRW V0, V1, 5
+    SE VF, 0
+    LD VC, 1
+    DRW V0, V1, 5
+    SE VF, 1
+    LD VC, 1
+    DRW V0, V1, 5
+    CLS
+    DRW V0, V1, 5
+    SE VF, 0
+    LD VC, 1
+    CLS
+    CALL result
+
+; 2
+    LD V0, 0x12
+    LD V1, 0x12
+    LD V2, 0x34
+    SE V0, 0x12
+    LD VC, 1
+    SNE V0, 0x34
+    LD VC, 1
+    SE V0, V1
+    LD VC, 1
+    SNE V0, V2
+    LD VC, 1
+    LD V3, 0
+    SE V0, 0x34
+    ADD V3, 1
+    SNE V0, 0x12
+    ADD V3, 1
+    SE V0, V2
+    ADD V3, 1
+    SNE V0, V1
+    ADD V3, 1
+    SE V3, 4
+    LD VC, 1
+    CALL result
+
+; 3
+    LD VF, 5
+    LD V0, 0xff
+    ADD V0, 0x02
+    SE V0, 0x01
+    LD VC, 1
+    SE VF, 5
+    LD VC, 1
+    LD V1, V0
+    SE V1, 0x01
+    LD VC, 1
+    CALL result
+
+; 4
+    LD V1, 0x0a
+    LD V0, 0x0c
+    OR V0, V1
+    SE V0, 0x0e
+    LD VC, 1
+    LD V0, 0x0c
+    AND V0, V1
+    SE V0, 0x08
+    LD VC, 1
+    LD V0, 0x0c
+    XOR V0, V1
+    SE V0, 0x06
+    LD VC, 1
+    CALL result
+
+; 5
+    LD V0, 0xff
+    LD V1, 0x02
+    ADD V0, V1
+    SE V0, 0x01
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    LD V0, 0x10
+    LD V1, 0x20
+    ADD V0, V1
+    SE V0, 0x30
+    LD VC, 1
+    SE VF, 0
+    LD VC, 1
+    CALL result
+
+; 6
+    LD V0, 5
+    LD V1, 3
+    SUB V0, V1
+    SE V0, 2
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    LD V0, 3
+    LD V1, 5
+    SUB V0, V1
+    SE V0, 0xfe
+    LD VC, 1
+    SE VF, 0
+    LD VC, 1
+    LD V0, 5
+    LD V1, 5
+    SUB V0, V1
+    SE V0, 0
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    CALL result
+
+; 7
+    LD V0, 5
+    SHR V0, V0
+    SE V0, 2
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    LD V0, 4
+    SHR V0, V0
+    SE V0, 2
+    LD VC, 1
+    SE VF, 0
+    LD VC, 1
+    CALL result
+
+; 8
+    LD V0, 3
+    LD V1, 5
+    SUBN V0, V1
+    SE V0, 2
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    LD V0, 5
+    LD V1, 3
+    SUBN V0, V1
+    SE V0, 0xfe
+    LD VC, 1
+    SE VF, 0
+    LD VC, 1
+    CALL result
+
+; 9
+    LD V0, 0x81
+    SHL V0, V0
+    SE V0, 0x02
+    LD VC, 1
+    SE VF, 1
+    LD VC, 1
+    LD V0, 0x41
+    SHL V0, V0
+    SE V0, 0x82
+    LD VC, 1
+    SE VF, 0
+    LD VC, 1
+    CALL result
+
+; 10
+    LD VF, 0x10
+    LD V1, 0x20
+    ADD VF, V1
+    SE VF, 0
+    LD VC, 1
+    LD VF, 0xff
+    LD V1, 0x02
+    ADD VF, V1
+    SE VF, 1
+    LD VC, 1
+    LD VF, 5
+    LD V1, 3
+    SUB VF, V1
+    SE VF, 1
+    LD VC, 1
+    LD VF, 3
+    LD V1, 5
+    SUB VF, V1
+    SE VF, 0
+    LD VC, 1
+    LD VF, 4
+    SHR VF, VF
+    SE VF, 0
+    LD VC, 1
+    LD VF, 0x81
+    SHL VF, VF
+    SE VF, 1
+    LD VC, 1
+    CALL result
+
+; 11
+    LD I, scratch
+    LD V0, 0x12
+    LD V1, 0x34
+    LD [I], V1
+    LD V0, 0
+    LD V1, 0
+    LD I, scratch
+    LD V1, [I]
+    SE V0, 0x12
+    LD VC, 1
+    SE V1, 0x34
+    LD VC, 1
+    LD I, scratch
+    LD V2, 1
+    ADD I, V2
+    LD V0, [I]
+    SE V0, 0x34
+    LD VC, 1
+    CALL result
+
+; 12
+    LD V0, 123
+    LD I, scratch
+    LD B, V0
+    LD V2, [I]
+    SE V0, 1
+    LD VC, 1
+    SE V1, 2
+    LD VC, 1
+    SE V2, 3
+    LD VC, 1
+    CALL result
+
+; 13
+    LD V0, 0
+    CALL set_42
+    SE V0, 0x42
+    LD VC, 1
+    CALL result
+
+; 14
+    LD V0, 2
+    JP V0, jump_table
+jump_table:
+    JP jump_bad
+    JP jump_ok
+jump_bad:
+    LD VC, 1
+jump_ok:
+    CALL result
+
+; 15
+    LD V0, 10
+    LD DT, V0
+    LD V1, DT
+    SNE V1, 0
+    LD VC, 1
+    CALL result
+
+; 16
+    LD V0, 1
+    LD F, V0
+    LD V0, [I]
+    SE V0, 0x20
+    LD VC, 1
+    CALL result
+
+; 17
+    RND V0, 0x00
+    SE V0, 0
+    LD VC, 1
+    RND V0, 0x0f
+    LD V1, 0xf0
+    AND V1, V0
+    SE V1, 0
+    LD VC, 1
+    CALL result
+
+; 18
+    LD V0, 5
+    SKNP V0
+    LD VC, 1
+    LD V3, 0
+    SKP V0
+    LD V3, 1
+    SE V3, 1
+    LD VC, 1
+    CALL result
+
+end:
+    JP end
+
+set_42:
+    LD V0, 0x42
+    RET
+
+; Draws a tick or a cross at (VE, VD) depending on VC, then moves on.
+result:
+    LD I, ok
+    SE VC, 0
+    LD I, bad
+    DRW VE, VD, 5
+    LD VC, 0
+    ADD VE, 8
+    SE VE, 64
+    RET
+    LD VE, 0
+    ADD VD, 6
+    RET
+
+ok:
+    db "......##"
+    db ".....##."
+    db "##..##.."
+    db ".####..."
+    db "..##...."
+bad:
+    db "##...##."
+    db ".##.##.."
+    db "..###..."
+    db ".##.##.."
+    db "##...##."
+scratch:
+    db 0, 0, 0, 0
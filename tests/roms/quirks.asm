@@ -0,0 +1,115 @@
+; Quirks detection test.
+;
+; Draws one digit per quirk, 1 when the interpreter shows the quirky
+; behaviour and 0 when it doesn't, in this order:
+;
+;   shift_uses_vy load_store_increments_i jump_uses_vx
+;   logic_resets_vf clip_sprites display_wait
+;   load_store_increments_i_by_x super_chip
+
+start:
+    LD VD, 0
+    LD VE, 0
+
+; shift_uses_vy: SHR shifts V1 into V0 instead of V0 itself.
+    LD V0, 0xff
+    LD V1, 0x04
+    SHR V0, V1
+    LD VA, 0
+    SNE V0, 0x02
+    LD VA, 1
+    CALL digit
+
+; load_store_increments_i: the second load reads the next byte.
+    LD I, markers
+    LD V0, [I]
+    LD V0, [I]
+    LD VA, 0
+    SNE V0, 0xbb
+    LD VA, 1
+    CALL digit
+
+; jump_uses_vx: Bnnn adds Vx, x being the high nibble of nnn, not V0.
+    LD V0, 0
+    LD V2, 2
+    LD V3, 2
+    LD VA, 0
+    JP V0, jump_table
+jump_table:
+    JP jump_done
+    LD VA, 1
+jump_done:
+    CALL digit
+
+; logic_resets_vf: OR clears VF.
+    LD VF, 5
+    OR V0, V1
+    LD VA, 0
+    SNE VF, 0
+    LD VA, 1
+    CALL digit
+
+; clip_sprites: a sprite at the right edge doesn't wrap onto column 0.
+    LD I, pixel_row
+    LD V0, 60
+    LD V1, 31
+    LD V2, 0
+    DRW V0, V1, 1
+    DRW V2, V1, 1
+    LD VA, 1
+    SE VF, 0
+    LD VA, 0
+    DRW V0, V1, 1
+    DRW V2, V1, 1
+    CALL digit
+
+; display_wait: five sprites take five frames and run the delay timer out.
+    LD I, blank
+    LD V0, 3
+    LD DT, V0
+    DRW V1, V1, 1
+    DRW V1, V1, 1
+    DRW V1, V1, 1
+    DRW V1, V1, 1
+    DRW V1, V1, 1
+    LD V0, DT
+    LD VA, 0
+    SNE V0, 0
+    LD VA, 1
+    CALL digit
+
+; load_store_increments_i_by_x: loading V0-V1 leaves I on the second byte.
+    LD I, markers
+    LD V1, [I]
+    LD V0, [I]
+    LD VA, 0
+    SNE V0, 0xbb
+    LD VA, 1
+    CALL digit
+
+; super_chip: Dxy0 draws a 16x16 sprite, so drawing it twice collides.
+    LD I, markers
+    LD V0, 40
+    LD V1, 12
+    DRW V0, V1, 0
+    DRW V0, V1, 0
+    LD VA, 0
+    SE VF, 0
+    LD VA, 1
+    CALL digit
+
+end:
+    JP end
+
+digit:
+    LD F, VA
+    DRW VE, VD, 5
+    ADD VE, 5
+    RET
+
+markers:
+    db 0xaa, 0xbb, 0xcc
+pixel_row:
+    db 0xff
+blank:
+    db 0
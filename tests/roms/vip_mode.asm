@@ -0,0 +1,32 @@
+; SUPER-CHIP instructions on a machine without them.
+;
+; 00Cn and 00FB-00FF are machine code calls there, which are ignored, and
+; Dxy0 draws nothing. The two digits stay where they were drawn, in lo-res,
+; and the program runs on past EXIT to the end.
+
+start:
+    LD I, 0
+    LD V0, 2
+    LD V1, 2
+    DRW V0, V1, 5
+    SCD 4
+    SCR
+    SCL
+    HIGH
+    LOW
+    EXIT
+    LD I, sprite
+    LD V0, 20
+    DRW V0, V1, 0
+    LD V0, 8
+    LD I, 5
+    DRW V0, V1, 5
+
+end:
+    JP end
+
+sprite:
+    db 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
+    db 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
+    db 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
+    db 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
@@ -0,0 +1,48 @@
+use chip8_emu::{Chip8, Speed};
+
+// An endless loop, so every cycle runs an instruction.
+fn looping() -> Chip8 {
+    Chip8::new(&[0x12, 0x00])
+}
+
+#[test]
+fn exact_instruction_rates() {
+    for &ips in &[1, 30, 59, 540, 700, 1000, 1_000_000] {
+        let mut chip8 = looping();
+        chip8.set_instructions_per_second(ips);
+        assert_eq!(chip8.instructions_per_second(), ips);
+        for second in 1..=3 {
+            for _ in 0..60 {
+                chip8.step_frame().unwrap();
+            }
+            assert_eq!(chip8.frames(), second * 60, "{} IPS", ips);
+            assert_eq!(chip8.cycles(), second * u64::from(ips), "{} IPS", ips);
+        }
+    }
+}
+
+#[test]
+fn cycles_keep_the_frame_rate() {
+    // Stepping cycle by cycle ends the same frames as stepping by frame.
+    let mut chip8 = looping();
+    chip8.set_instructions_per_second(30);
+    while chip8.cycles() < 30 {
+        chip8.step().unwrap();
+    }
+    assert_eq!(chip8.frames(), 60);
+}
+
+#[test]
+fn speed_steps() {
+    let mut chip8 = looping();
+    chip8.set_instructions_per_second(700);
+    Speed::faster(&mut chip8);
+    assert_eq!(chip8.instructions_per_second(), 875);
+    Speed::slower(&mut chip8);
+    assert_eq!(chip8.instructions_per_second(), 700);
+    chip8.set_instructions_per_second(1);
+    Speed::slower(&mut chip8);
+    assert_eq!(chip8.instructions_per_second(), 1);
+    Speed::faster(&mut chip8);
+    assert_eq!(chip8.instructions_per_second(), 2);
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::Path;
+
+use chip8_emu::state::{MAGIC, VERSION};
+use chip8_emu::{Chip8, StateError};
+
+fn breakout() -> Chip8 {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("Breakout [Carmelo Cortez, 1979].ch8");
+    Chip8::with_seed(&fs::read(path).unwrap(), 1)
+}
+
+// Runs `frames` frames holding key 6 down for the first half.
+fn play(chip8: &mut Chip8, frames: u64) {
+    for frame in 0..frames {
+        chip8.set_key(0x6, frame < frames / 2);
+        chip8.step_frame().unwrap();
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut chip8 = breakout();
+    play(&mut chip8, 100);
+    let state = chip8.save_state();
+    assert_eq!(state[..4], MAGIC);
+    assert_eq!(state[4..6], VERSION.to_le_bytes());
+    play(&mut chip8, 100);
+    let expected = chip8.save_state();
+
+    // A fresh machine picks up exactly where the saved one was, RNG included.
+    let mut restored = Chip8::new(&[]);
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    play(&mut restored, 100);
+    assert_eq!(restored.save_state(), expected);
+    assert_eq!(restored.display(), chip8.display());
+}
+
+#[test]
+fn rejects_bad_states() {
+    let mut chip8 = breakout();
+    play(&mut chip8, 10);
+    let state = chip8.save_state();
+    let mut other = Chip8::new(&[0x12, 0x00]);
+    let untouched = other.save_state();
+
+    let mut bad_magic = state.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(other.load_state(&bad_magic), Err(StateError::NotAState));
+    assert_eq!(other.load_state(b"CH8"), Err(StateError::NotAState));
+
+    let mut bad_version = state.clone();
+    bad_version[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert_eq!(other.load_state(&bad_version), Err(StateError::UnsupportedVersion { version: VERSION + 1 }));
+
+    assert_eq!(other.load_state(&state[..state.len() - 1]), Err(StateError::Truncated));
+    assert_eq!(other.load_state(&state[..5]), Err(StateError::Truncated));
+
+    let mut bad_memory_size = state.clone();
+    bad_memory_size[6..10].copy_from_slice(&100u32.to_le_bytes());
+    assert_eq!(other.load_state(&bad_memory_size), Err(StateError::Invalid { field: "memory size" }));
+
+    let mut trailing = state;
+    trailing.push(0);
+    assert_eq!(other.load_state(&trailing), Err(StateError::Invalid { field: "length" }));
+
+    assert_eq!(other.save_state(), untouched);
+}
@@ -0,0 +1,106 @@
+use std::io;
+use std::io::prelude::*;
+use std::sync::{Arc, Mutex};
+
+use chip8_emu::{Chip8, TraceFilter, TraceFormat, Tracer};
+
+// 200: LD V0, 5; LD DT, V0; LD ST, V0; CALL 20a; JP 208; 20a: RET
+const ROM: [u8; 12] = [0x60, 0x05, 0xf0, 0x15, 0xf0, 0x18, 0x22, 0x0a, 0x12, 0x08, 0x00, 0xee];
+
+// A writer whose output the test can still read once the tracer owns it.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn trace(format: TraceFormat, filter: TraceFilter, steps: usize) -> String {
+    let output = Output::default();
+    let mut tracer = Tracer::new(Box::new(output.clone()), format);
+    tracer.set_filter(filter);
+    let mut chip8 = Chip8::new(&ROM);
+    chip8.set_tracer(Some(tracer));
+    for _ in 0..steps {
+        chip8.step().unwrap();
+    }
+    chip8.set_tracer(None);
+    let text = output.0.lock().unwrap().clone();
+    String::from_utf8(text).unwrap()
+}
+
+#[test]
+fn text() {
+    let lines = [
+        "        0 0200 6005 LD V0, 0x05              V0=00->05",
+        "        1 0202 f015 LD DT, V0                DT=00->05",
+        "        2 0204 f018 LD ST, V0                ST=00->05",
+        "        3 0206 220a CALL 0x20a               PC=0206->020a SP=0->1",
+        "        4 020a 00ee RET                      PC=020a->0208 SP=1->0",
+        "        5 0208 1208 JP 0x208                 PC=0208->0208",
+    ];
+    assert_eq!(trace(TraceFormat::Text, TraceFilter::default(), 6), lines.join("\n") + "\n");
+}
+
+#[test]
+fn json_lines() {
+    let text = trace(TraceFormat::JsonLines, TraceFilter::default(), 4);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[3],
+        "{\"cycle\":3,\"pc\":518,\"opcode\":8714,\"mnemonic\":\"CALL 0x20a\",\
+        \"v_before\":[5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],\"i_before\":0,\"sp_before\":0,\
+        \"dt_before\":5,\"st_before\":5,\
+        \"v_after\":[5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],\"i_after\":0,\"pc_after\":522,\
+        \"sp_after\":1,\"dt_after\":5,\"st_after\":5}",
+    );
+}
+
+#[test]
+fn filters() {
+    let classes = TraceFilter { addresses: 0..=0xffff, classes: TraceFilter::parse_classes("2,f").unwrap() };
+    let text = trace(TraceFormat::Text, classes, 6);
+    let opcodes: Vec<&str> = text.lines().map(|line| line.split_whitespace().nth(2).unwrap()).collect();
+    assert_eq!(opcodes, ["f015", "f018", "220a"]);
+
+    let addresses = TraceFilter { addresses: TraceFilter::parse_addresses("204-208").unwrap(), classes: 0xffff };
+    let text = trace(TraceFormat::Text, addresses, 6);
+    let pcs: Vec<&str> = text.lines().map(|line| line.split_whitespace().nth(1).unwrap()).collect();
+    assert_eq!(pcs, ["0204", "0206", "0208"]);
+
+    assert_eq!(TraceFilter::parse_addresses("0x200"), Some(0x200..=0x200));
+    assert_eq!(TraceFilter::parse_addresses("200-"), None);
+    assert_eq!(TraceFilter::parse_classes("1,10"), None);
+}
+
+// Fails every write.
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_errors_stop_tracing() {
+    let mut chip8 = Chip8::new(&ROM);
+    chip8.set_tracer(Some(Tracer::new(Box::new(Broken), TraceFormat::Text)));
+    chip8.step().unwrap();
+    chip8.step().unwrap();
+    let tracer = chip8.set_tracer(None).unwrap();
+    assert!(!tracer.is_enabled());
+    assert_eq!(tracer.error().unwrap().to_string(), "disk full");
+}